@@ -0,0 +1,99 @@
+use serde::Deserialize;
+
+use crate::world::chunk::BlockId;
+
+use super::face::Face;
+
+/// A block type, as described in `src/assets/blocks.ron`.
+#[derive(Debug, Clone, Deserialize)]
+pub struct BlockDefinition {
+    pub id: BlockId,
+    pub name: String,
+    /// Whether entities collide with the block.
+    #[serde(default = "default_true")]
+    pub solid: bool,
+    /// Whether neighbouring faces can be seen through the block.
+    #[serde(default)]
+    pub transparent: bool,
+    /// Blocks without textures are never drawn.
+    #[serde(default)]
+    pub textures: Option<FaceTextures>,
+    /// Block light level emitted, from 0 to 15.
+    #[serde(default)]
+    pub light_emission: u8,
+    #[serde(default = "default_hardness")]
+    pub hardness: f32,
+}
+
+impl BlockDefinition {
+    pub fn air() -> Self {
+        Self {
+            id: 0,
+            name: "air".to_string(),
+            solid: false,
+            transparent: true,
+            textures: None,
+            light_emission: 0,
+            hardness: 0.0,
+        }
+    }
+
+    pub fn texture(&self, face: Face) -> Option<&str> {
+        self.textures.as_ref().map(|textures| textures.get(face))
+    }
+}
+
+/// Texture names for each face of a block.
+#[derive(Debug, Clone, Deserialize)]
+pub enum FaceTextures {
+    All(String),
+    TopBottomSides {
+        top: String,
+        bottom: String,
+        sides: String,
+    },
+    PerFace {
+        east: String,
+        west: String,
+        top: String,
+        bottom: String,
+        south: String,
+        north: String,
+    },
+}
+
+impl FaceTextures {
+    pub fn get(&self, face: Face) -> &str {
+        match self {
+            FaceTextures::All(name) => name,
+            FaceTextures::TopBottomSides { top, bottom, sides } => match face {
+                Face::Top => top,
+                Face::Bottom => bottom,
+                _ => sides,
+            },
+            FaceTextures::PerFace {
+                east,
+                west,
+                top,
+                bottom,
+                south,
+                north,
+            } => match face {
+                Face::East => east,
+                Face::West => west,
+                Face::Top => top,
+                Face::Bottom => bottom,
+                Face::South => south,
+                Face::North => north,
+            },
+        }
+    }
+}
+
+fn default_true() -> bool {
+    true
+}
+
+fn default_hardness() -> f32 {
+    1.0
+}
@@ -0,0 +1,47 @@
+use ultraviolet as uv;
+
+/// One of the six faces of a block.
+///
+/// East/West run along the x axis, Top/Bottom along y and South/North along z.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Face {
+    East,
+    West,
+    Top,
+    Bottom,
+    South,
+    North,
+}
+
+impl Face {
+    pub const ALL: [Face; 6] = [
+        Face::East,
+        Face::West,
+        Face::Top,
+        Face::Bottom,
+        Face::South,
+        Face::North,
+    ];
+
+    /// Position of the face in `Face::ALL`.
+    pub fn index(&self) -> usize {
+        *self as usize
+    }
+
+    /// Offset to the neighbouring block this face looks at.
+    pub fn offset(&self) -> uv::IVec3 {
+        match self {
+            Face::East => uv::IVec3::new(1, 0, 0),
+            Face::West => uv::IVec3::new(-1, 0, 0),
+            Face::Top => uv::IVec3::new(0, 1, 0),
+            Face::Bottom => uv::IVec3::new(0, -1, 0),
+            Face::South => uv::IVec3::new(0, 0, 1),
+            Face::North => uv::IVec3::new(0, 0, -1),
+        }
+    }
+
+    pub fn normal(&self) -> uv::Vec3 {
+        let offset = self.offset();
+        uv::Vec3::new(offset.x as f32, offset.y as f32, offset.z as f32)
+    }
+}
@@ -0,0 +1,3 @@
+pub mod definition;
+pub mod face;
+pub mod registry;
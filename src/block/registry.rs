@@ -0,0 +1,336 @@
+use std::{collections::HashMap, fmt, fs, path::Path};
+
+use log::info;
+
+use crate::world::chunk::{BlockId, AIR};
+
+use super::{definition::BlockDefinition, face::Face};
+
+/// Maps block IDs to their definitions.
+///
+/// ID 0 is reserved for air, which is always registered.
+pub struct BlockRegistry {
+    // Indexed by block ID. IDs that aren't defined are None.
+    blocks: Vec<Option<BlockDefinition>>,
+    ids_by_name: HashMap<String, BlockId>,
+}
+
+impl BlockRegistry {
+    /// Loads block definitions from a RON file.
+    ///
+    /// Every texture a block refers to must exist as `<texture_dir>/<name>.png`.
+    pub fn load(path: &str, texture_dir: &str) -> Result<Self, BlockRegistryError> {
+        info!("Loading block definitions from {}", path);
+        let source = fs::read_to_string(path).map_err(|error| BlockRegistryError::Io {
+            path: path.to_string(),
+            error,
+        })?;
+        let definitions: Vec<BlockDefinition> =
+            ron::from_str(&source).map_err(|error| BlockRegistryError::Parse {
+                path: path.to_string(),
+                error,
+            })?;
+
+        let registry = Self::from_definitions(definitions, |texture| {
+            Path::new(texture_dir)
+                .join(format!("{}.png", texture))
+                .is_file()
+        })?;
+        info!("Loaded {} block definitions", registry.ids_by_name.len());
+        Ok(registry)
+    }
+
+    /// Builds a registry from definitions, using `texture_exists` to check
+    /// that every referenced texture is available.
+    pub fn from_definitions(
+        definitions: Vec<BlockDefinition>,
+        texture_exists: impl Fn(&str) -> bool,
+    ) -> Result<Self, BlockRegistryError> {
+        let mut registry = Self {
+            blocks: vec![Some(BlockDefinition::air())],
+            ids_by_name: HashMap::from([("air".to_string(), AIR)]),
+        };
+
+        for definition in definitions {
+            registry.register(definition, &texture_exists)?;
+        }
+
+        Ok(registry)
+    }
+
+    fn register(
+        &mut self,
+        definition: BlockDefinition,
+        texture_exists: &impl Fn(&str) -> bool,
+    ) -> Result<(), BlockRegistryError> {
+        if definition.id == AIR {
+            return Err(BlockRegistryError::ReservedId {
+                name: definition.name,
+            });
+        }
+        if self.ids_by_name.contains_key(&definition.name) {
+            return Err(BlockRegistryError::DuplicateName {
+                name: definition.name,
+            });
+        }
+        if let Some(existing) = self.get(definition.id) {
+            return Err(BlockRegistryError::DuplicateId {
+                id: definition.id,
+                first: existing.name.clone(),
+                second: definition.name,
+            });
+        }
+        if definition.light_emission > 15 {
+            return Err(BlockRegistryError::InvalidLightEmission {
+                name: definition.name,
+                light_emission: definition.light_emission,
+            });
+        }
+        for face in Face::ALL {
+            if let Some(texture) = definition.texture(face) {
+                if !texture_exists(texture) {
+                    return Err(BlockRegistryError::MissingTexture {
+                        block: definition.name.clone(),
+                        texture: texture.to_string(),
+                    });
+                }
+            }
+        }
+
+        let index = definition.id as usize;
+        if index >= self.blocks.len() {
+            self.blocks.resize(index + 1, None);
+        }
+        self.ids_by_name
+            .insert(definition.name.clone(), definition.id);
+        self.blocks[index] = Some(definition);
+        Ok(())
+    }
+
+    pub fn get(&self, id: BlockId) -> Option<&BlockDefinition> {
+        self.blocks.get(id as usize).and_then(Option::as_ref)
+    }
+
+    pub fn id(&self, name: &str) -> Option<BlockId> {
+        self.ids_by_name.get(name).copied()
+    }
+
+    /// Like `id`, but for blocks the game can't work without.
+    pub fn require(&self, name: &str) -> Result<BlockId, BlockRegistryError> {
+        self.id(name)
+            .ok_or_else(|| BlockRegistryError::UnknownBlock {
+                name: name.to_string(),
+            })
+    }
+
+    /// Whether a block hides the faces of blocks next to it.
+    ///
+    /// Unknown IDs are treated like air.
+    pub fn is_opaque(&self, id: BlockId) -> bool {
+        self.get(id).is_some_and(|block| !block.transparent)
+    }
+
+    pub fn is_solid(&self, id: BlockId) -> bool {
+        self.get(id).is_some_and(|block| block.solid)
+    }
+
+    pub fn blocks(&self) -> impl Iterator<Item = &BlockDefinition> {
+        self.blocks.iter().flatten()
+    }
+
+    /// Every texture used by a block, each listed once in the order blocks
+    /// were defined.
+    pub fn texture_names(&self) -> Vec<&str> {
+        let mut names: Vec<&str> = Vec::new();
+        for block in self.blocks() {
+            for face in Face::ALL {
+                if let Some(texture) = block.texture(face) {
+                    if !names.contains(&texture) {
+                        names.push(texture);
+                    }
+                }
+            }
+        }
+        names
+    }
+}
+
+#[derive(Debug)]
+pub enum BlockRegistryError {
+    Io {
+        path: String,
+        error: std::io::Error,
+    },
+    Parse {
+        path: String,
+        error: ron::error::SpannedError,
+    },
+    ReservedId {
+        name: String,
+    },
+    DuplicateName {
+        name: String,
+    },
+    DuplicateId {
+        id: BlockId,
+        first: String,
+        second: String,
+    },
+    InvalidLightEmission {
+        name: String,
+        light_emission: u8,
+    },
+    MissingTexture {
+        block: String,
+        texture: String,
+    },
+    UnknownBlock {
+        name: String,
+    },
+}
+
+impl fmt::Display for BlockRegistryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BlockRegistryError::Io { path, error } => {
+                write!(f, "failed to read block definitions at {}: {}", path, error)
+            }
+            BlockRegistryError::Parse { path, error } => {
+                write!(
+                    f,
+                    "failed to parse block definitions at {}: {}",
+                    path, error
+                )
+            }
+            BlockRegistryError::ReservedId { name } => {
+                write!(f, "block \"{}\" uses ID 0, which is reserved for air", name)
+            }
+            BlockRegistryError::DuplicateName { name } => {
+                write!(f, "block \"{}\" is defined more than once", name)
+            }
+            BlockRegistryError::DuplicateId { id, first, second } => write!(
+                f,
+                "blocks \"{}\" and \"{}\" both use ID {}",
+                first, second, id
+            ),
+            BlockRegistryError::InvalidLightEmission {
+                name,
+                light_emission,
+            } => write!(
+                f,
+                "block \"{}\" emits light level {}, but the maximum is 15",
+                name, light_emission
+            ),
+            BlockRegistryError::MissingTexture { block, texture } => write!(
+                f,
+                "block \"{}\" uses texture \"{}\", which doesn't exist",
+                block, texture
+            ),
+            BlockRegistryError::UnknownBlock { name } => {
+                write!(f, "block \"{}\" is required but isn't defined", name)
+            }
+        }
+    }
+}
+
+impl std::error::Error for BlockRegistryError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            BlockRegistryError::Io { error, .. } => Some(error),
+            BlockRegistryError::Parse { error, .. } => Some(error),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn definitions(source: &str) -> Vec<BlockDefinition> {
+        ron::from_str(&format!("#![enable(implicit_some)]\n{}", source)).unwrap()
+    }
+
+    fn build(source: &str) -> Result<BlockRegistry, BlockRegistryError> {
+        BlockRegistry::from_definitions(definitions(source), |_| true)
+    }
+
+    #[test]
+    fn valid_definitions_load_and_resolve_names() {
+        let registry = build(
+            r#"[
+                (id: 1, name: "stone", textures: All("stone")),
+                (id: 5, name: "glowstone", textures: All("glowstone"), light_emission: 15),
+            ]"#,
+        )
+        .unwrap();
+
+        assert_eq!(registry.id("air"), Some(AIR));
+        assert_eq!(registry.id("stone"), Some(1));
+        assert_eq!(registry.id("glowstone"), Some(5));
+        assert_eq!(registry.id("dirt"), None);
+        assert_eq!(registry.get(5).unwrap().light_emission, 15);
+        assert!(registry.get(2).is_none());
+        assert!(registry.is_opaque(1));
+        assert!(!registry.is_opaque(AIR));
+    }
+
+    #[test]
+    fn id_zero_is_reserved_for_air() {
+        let result = build(r#"[(id: 0, name: "stone")]"#);
+        assert!(matches!(
+            result,
+            Err(BlockRegistryError::ReservedId { name }) if name == "stone"
+        ));
+    }
+
+    #[test]
+    fn names_must_be_unique() {
+        let result = build(r#"[(id: 1, name: "stone"), (id: 2, name: "stone")]"#);
+        assert!(matches!(
+            result,
+            Err(BlockRegistryError::DuplicateName { name }) if name == "stone"
+        ));
+    }
+
+    #[test]
+    fn ids_must_be_unique() {
+        let result = build(r#"[(id: 1, name: "stone"), (id: 1, name: "dirt")]"#);
+        assert!(matches!(
+            result,
+            Err(BlockRegistryError::DuplicateId { id: 1, first, second })
+                if first == "stone" && second == "dirt"
+        ));
+    }
+
+    #[test]
+    fn light_emission_is_at_most_fifteen() {
+        let result = build(r#"[(id: 1, name: "glowstone", light_emission: 16)]"#);
+        assert!(matches!(
+            result,
+            Err(BlockRegistryError::InvalidLightEmission {
+                name,
+                light_emission: 16,
+            }) if name == "glowstone"
+        ));
+    }
+
+    #[test]
+    fn textures_must_exist() {
+        let result = BlockRegistry::from_definitions(
+            definitions(
+                r#"[(
+                    id: 1,
+                    name: "grass",
+                    textures: TopBottomSides(top: "grass_top", bottom: "dirt", sides: "grass_side"),
+                )]"#,
+            ),
+            |texture| texture != "grass_side",
+        );
+        assert!(matches!(
+            result,
+            Err(BlockRegistryError::MissingTexture { block, texture })
+                if block == "grass" && texture == "grass_side"
+        ));
+    }
+}
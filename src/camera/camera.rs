@@ -1,7 +1,8 @@
 use std::f32::consts::PI;
 
-use ultraviolet as uv;
+use ultraviolet::{self as uv, Lerp};
 
+#[derive(Debug, Clone)]
 pub struct Camera {
     pub eye: uv::Vec3,
     pub target: uv::Vec3,
@@ -21,7 +22,7 @@ impl Camera {
             aspect,
             fov_y_rad: PI / 4.0,
             z_near: 0.1,
-            z_far: 100.0,
+            z_far: 500.0,
         }
     }
 
@@ -35,4 +36,42 @@ impl Camera {
         );
         projection * view
     }
+
+    /// The camera part way from `previous` to this one, where an alpha of 0
+    /// is `previous` and 1 is this camera.
+    pub fn interpolate(&self, previous: &Camera, alpha: f32) -> Camera {
+        Camera {
+            eye: previous.eye.lerp(self.eye, alpha),
+            target: previous.target.lerp(self.target, alpha),
+            up: previous.up.lerp(self.up, alpha).normalized(),
+            ..self.clone()
+        }
+    }
+
+    pub fn z_near(&self) -> f32 {
+        self.z_near
+    }
+
+    /// World positions of the corners of the slice of the view frustum
+    /// between two distances from the camera, near corners first.
+    pub fn frustum_corners(&self, near: f32, far: f32) -> [uv::Vec3; 8] {
+        let forward = (self.target - self.eye).normalized();
+        let right = forward.cross(self.up).normalized();
+        let up = right.cross(forward);
+        let tan_half_fov = (self.fov_y_rad / 2.0).tan();
+
+        let mut corners = [uv::Vec3::zero(); 8];
+        for (i, distance) in [near, far].into_iter().enumerate() {
+            let center = self.eye + forward * distance;
+            let half_height = distance * tan_half_fov;
+            let half_width = half_height * self.aspect;
+            for (j, (x, y)) in [(-1.0, -1.0), (-1.0, 1.0), (1.0, 1.0), (1.0, -1.0)]
+                .into_iter()
+                .enumerate()
+            {
+                corners[i * 4 + j] = center + right * (x * half_width) + up * (y * half_height);
+            }
+        }
+        corners
+    }
 }
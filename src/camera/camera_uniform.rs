@@ -1,10 +1,22 @@
+use bytemuck_derive::{Pod, Zeroable};
 use ultraviolet as uv;
 use wgpu::util::DeviceExt;
 
 use super::camera::Camera;
 
+/// The camera as laid out in the uniform buffer.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Pod, Zeroable)]
+pub struct CameraUniformRaw {
+    pub view_projection: uv::Mat4,
+    /// The camera's position, which lighting needs for specular highlights.
+    /// The w component is unused and pads it to 16 bytes.
+    pub view_position: uv::Vec4,
+}
+
 pub struct CameraUniform {
     pub view_projection: uv::Mat4,
+    pub view_position: uv::Vec4,
     pub bind_group: wgpu::BindGroup,
     pub buffer: wgpu::Buffer,
     pub layout: wgpu::BindGroupLayout,
@@ -13,10 +25,14 @@ pub struct CameraUniform {
 impl CameraUniform {
     pub fn new(camera: &Camera, device: &wgpu::Device) -> Self {
         let view_projection = camera.build_view_projection_matrix();
+        let view_position = camera.eye.into_homogeneous_point();
 
         let buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
             label: Some("Camera Buffer"),
-            contents: bytemuck::cast_slice(&[view_projection]),
+            contents: bytemuck::cast_slice(&[CameraUniformRaw {
+                view_projection,
+                view_position,
+            }]),
             usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
         });
 
@@ -24,7 +40,7 @@ impl CameraUniform {
             label: Some("Camera Bind Group Layout"),
             entries: &[wgpu::BindGroupLayoutEntry {
                 binding: 0,
-                visibility: wgpu::ShaderStages::VERTEX,
+                visibility: wgpu::ShaderStages::VERTEX | wgpu::ShaderStages::FRAGMENT,
                 ty: wgpu::BindingType::Buffer {
                     ty: wgpu::BufferBindingType::Uniform,
                     has_dynamic_offset: false,
@@ -48,10 +64,19 @@ impl CameraUniform {
             layout,
             bind_group,
             view_projection,
+            view_position,
         }
     }
 
     pub fn update_view_projection(&mut self, camera: &Camera) {
         self.view_projection = camera.build_view_projection_matrix();
+        self.view_position = camera.eye.into_homogeneous_point();
+    }
+
+    pub fn to_raw(&self) -> CameraUniformRaw {
+        CameraUniformRaw {
+            view_projection: self.view_projection,
+            view_position: self.view_position,
+        }
     }
 }
@@ -0,0 +1,94 @@
+use std::f32::consts::FRAC_PI_2;
+
+use ultraviolet as uv;
+
+use crate::input::{action::Action, tick_input::TickInput};
+
+use super::{camera::Camera, camera_controller::CameraController};
+
+/// Stops the camera just short of looking straight up or down, where the
+/// view matrix can't tell which way is up.
+const MAX_PITCH: f32 = FRAC_PI_2 - 0.01;
+
+/// Radians per second turned with a look stick pushed all the way.
+const STICK_TURN_SPEED: f32 = 2.5;
+
+/// Looks around with the mouse and flies relative to the way the camera
+/// faces, strafing left and right and moving straight up and down.
+pub struct FirstPersonController {
+    /// Distance moved per second.
+    speed: f32,
+    /// Radians turned per unit of mouse movement.
+    sensitivity: f32,
+    /// Radians around the y axis, where 0 looks along +x.
+    yaw: f32,
+    /// Radians above the horizon.
+    pitch: f32,
+}
+
+impl FirstPersonController {
+    /// A controller carrying on looking the way the camera already is.
+    pub fn new(camera: &Camera, speed: f32, sensitivity: f32) -> Self {
+        let direction = (camera.target - camera.eye).normalized();
+        Self {
+            speed,
+            sensitivity,
+            yaw: direction.z.atan2(direction.x),
+            pitch: direction
+                .y
+                .clamp(-1.0, 1.0)
+                .asin()
+                .clamp(-MAX_PITCH, MAX_PITCH),
+        }
+    }
+
+    fn look_direction(&self) -> uv::Vec3 {
+        uv::Vec3::new(
+            self.pitch.cos() * self.yaw.cos(),
+            self.pitch.sin(),
+            self.pitch.cos() * self.yaw.sin(),
+        )
+    }
+}
+
+impl CameraController for FirstPersonController {
+    fn update_camera(&mut self, camera: &mut Camera, input: &TickInput, delta_time: f32) {
+        // Mouse movement is already a distance rather than a speed, so isn't
+        // scaled by delta time, unlike a look stick.
+        let (yaw, pitch) = (input.mouse_delta.0 as f32, input.mouse_delta.1 as f32);
+        let stick_turn = STICK_TURN_SPEED * delta_time;
+        let stick_yaw =
+            input.action_value(Action::LookRight) - input.action_value(Action::LookLeft);
+        let stick_pitch = input.action_value(Action::LookUp) - input.action_value(Action::LookDown);
+        self.yaw = (self.yaw + yaw * self.sensitivity + stick_yaw * stick_turn)
+            .rem_euclid(std::f32::consts::TAU);
+        // Moving the mouse down looks down.
+        self.pitch = (self.pitch - pitch * self.sensitivity + stick_pitch * stick_turn)
+            .clamp(-MAX_PITCH, MAX_PITCH);
+
+        // Moving forwards stays level, rather than following the pitch.
+        let forward = uv::Vec3::new(self.yaw.cos(), 0.0, self.yaw.sin());
+        let right = forward.cross(uv::Vec3::unit_y());
+        let mut movement = uv::Vec3::zero();
+        let directions = [
+            (Action::MoveForward, forward),
+            (Action::MoveBackward, -forward),
+            (Action::MoveRight, right),
+            (Action::MoveLeft, -right),
+            (Action::MoveUp, uv::Vec3::unit_y()),
+            (Action::MoveDown, -uv::Vec3::unit_y()),
+        ];
+        for (action, direction) in directions {
+            movement += direction * input.action_value(action);
+        }
+        // Moving diagonally is no faster than moving straight, while a stick
+        // pushed part way moves slower.
+        if movement.mag_sq() > 1.0 {
+            movement.normalize();
+        }
+        camera.eye += movement * self.speed * delta_time;
+
+        camera.up = uv::Vec3::unit_y();
+        camera.target = camera.eye + self.look_direction();
+    }
+}
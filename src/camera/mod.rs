@@ -1,3 +1,6 @@
+#[allow(clippy::module_inception)]
 pub mod camera;
 pub mod camera_controller;
 pub mod camera_uniform;
+pub mod first_person_controller;
+pub mod orbit_controller;
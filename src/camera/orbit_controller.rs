@@ -0,0 +1,49 @@
+use crate::input::{action::Action, tick_input::TickInput};
+
+use super::{camera::Camera, camera_controller::CameraController};
+
+/// Circles the camera around its target when moving left and right, and
+/// moves towards and away from it when moving forwards and backwards.
+pub struct OrbitController {
+    /// Distance moved per second.
+    speed: f32,
+}
+
+impl OrbitController {
+    pub fn new(speed: f32) -> Self {
+        Self { speed }
+    }
+}
+
+impl CameraController for OrbitController {
+    fn update_camera(&mut self, camera: &mut Camera, input: &TickInput, delta_time: f32) {
+        // A stick pushed part way moves slower.
+        let step = |action| self.speed * delta_time * input.action_value(action);
+        let forward = camera.target - camera.eye;
+        let forward_norm = forward.normalized();
+        let forward_mag = forward.mag();
+
+        // Prevents glitching when camera gets too close to the
+        // center of the scene.
+        let forward_step = step(Action::MoveForward);
+        if forward_step > 0.0 && forward_mag > forward_step {
+            camera.eye += forward_norm * forward_step;
+        }
+        camera.eye -= forward_norm * step(Action::MoveBackward);
+
+        let right = forward_norm.cross(camera.up);
+
+        // Redo radius calc in case the fowrard/backward is pressed.
+        let forward = camera.target - camera.eye;
+        let forward_mag = forward.mag();
+
+        let sideways_step = step(Action::MoveRight) - step(Action::MoveLeft);
+        if sideways_step != 0.0 {
+            // Rescale the distance between the target and eye so
+            // that it doesn't change. The eye therefore still
+            // lies on the circle made by the target and eye.
+            camera.eye =
+                camera.target - (forward + right * sideways_step).normalized() * forward_mag;
+        }
+    }
+}
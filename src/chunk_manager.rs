@@ -0,0 +1,389 @@
+use std::collections::{HashMap, HashSet};
+
+use log::debug;
+use ultraviolet as uv;
+use wgpu::{Device, RenderPass};
+
+use crate::{
+    jobs::chunk_workers::{ChunkJobKind, ChunkJobOutput, ChunkWorkers},
+    meshing::{
+        chunk_mesh::{ChunkMesh, ChunkMeshBuffers},
+        mesher::MesherSettings,
+    },
+    world::{
+        chunk::{Chunk, ChunkPos},
+        deferred_writes::BlockWrite,
+        lighting::Lighting,
+        neighbourhood::NeighbourhoodSnapshot,
+        world::World,
+    },
+};
+
+pub const MIN_VIEW_DISTANCE: i32 = 2;
+pub const MAX_VIEW_DISTANCE: i32 = 32;
+
+#[derive(Debug, Clone, Copy)]
+pub struct StreamingSettings {
+    /// Chunks within this many chunks of the camera are loaded.
+    pub view_distance: i32,
+    /// How much further than the view distance a chunk has to be before it's
+    /// unloaded, so chunks on the border don't keep loading and unloading as
+    /// the camera moves back and forth.
+    pub unload_margin: i32,
+    /// How many generated chunks are added to the world each frame, at most.
+    pub chunks_added_per_frame: usize,
+}
+
+impl Default for StreamingSettings {
+    fn default() -> Self {
+        Self {
+            view_distance: 8,
+            unload_margin: 2,
+            chunks_added_per_frame: 4,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ChunkStats {
+    pub loaded: usize,
+    pub meshed: usize,
+    /// Chunks being generated, or waiting to be added to the world.
+    pub generating: usize,
+    pub meshing: usize,
+    /// Triangles across every meshed chunk, for comparing mesher settings.
+    pub triangles: usize,
+}
+
+/// Streams chunks in and out around the camera.
+///
+/// Generation and meshing happen on the workers. Each frame, `update` hands
+/// out new jobs and collects finished ones, which is the only time chunks are
+/// added to the world or meshes uploaded to the GPU.
+///
+/// Adding a chunk to the world happens on the main thread, since it changes
+/// the chunks around it too: writes from neighbouring features are applied,
+/// light is spread both ways along all four edges for the full height of the
+/// world, and every block written into a neighbour is relit. That's usually
+/// the most expensive part of a frame while chunks are streaming in, so only
+/// `chunks_added_per_frame` chunks are added each frame, nearest first, and
+/// the rest wait for later frames.
+pub struct ChunkManager {
+    world: World,
+    workers: ChunkWorkers,
+    /// Spreads light between chunks once they're in the world. Each chunk's
+    /// own light is worked out by the workers.
+    lighting: Lighting,
+    /// Chunks that have been generated but not yet added to the world.
+    generated: HashMap<ChunkPos, GeneratedChunk>,
+    meshes: HashMap<ChunkPos, ChunkMeshBuffers>,
+    needs_mesh: HashSet<ChunkPos>,
+    settings: StreamingSettings,
+}
+
+struct GeneratedChunk {
+    chunk: Chunk,
+    overflow: Vec<BlockWrite>,
+}
+
+impl ChunkManager {
+    pub fn new(workers: ChunkWorkers, lighting: Lighting, settings: StreamingSettings) -> Self {
+        let mut manager = Self {
+            world: World::new(),
+            workers,
+            lighting,
+            generated: HashMap::new(),
+            meshes: HashMap::new(),
+            needs_mesh: HashSet::new(),
+            settings,
+        };
+        manager.set_view_distance(settings.view_distance);
+        manager
+    }
+
+    pub fn settings(&self) -> StreamingSettings {
+        self.settings
+    }
+
+    /// Changes the view distance, clamped to a sensible range. Chunks are
+    /// loaded or unloaded on the next update.
+    pub fn set_view_distance(&mut self, view_distance: i32) {
+        self.settings.view_distance = view_distance.clamp(MIN_VIEW_DISTANCE, MAX_VIEW_DISTANCE);
+    }
+
+    pub fn stats(&self) -> ChunkStats {
+        ChunkStats {
+            loaded: self.world.chunk_count(),
+            meshed: self.meshes.len(),
+            generating: self.workers.pending_count_of(ChunkJobKind::Generate)
+                + self.generated.len(),
+            meshing: self.workers.pending_count_of(ChunkJobKind::Mesh),
+            triangles: self
+                .meshes
+                .values()
+                .map(|buffers| buffers.index_count as usize / 3)
+                .sum(),
+        }
+    }
+
+    pub fn mesher_settings(&self) -> MesherSettings {
+        self.workers.mesher_settings()
+    }
+
+    /// Changes how chunks are meshed, meshing every loaded chunk again.
+    /// Chunks keep their old meshes until the new ones arrive.
+    pub fn set_mesher_settings(&mut self, settings: MesherSettings) {
+        self.workers.set_mesher_settings(settings);
+        let loaded: Vec<ChunkPos> = self.world.chunks().map(|(pos, _)| *pos).collect();
+        self.needs_mesh.extend(loaded);
+    }
+
+    pub fn update(&mut self, device: &Device, eye: uv::Vec3) {
+        for (pos, mesh) in self.stream(eye) {
+            match ChunkMeshBuffers::new(device, &mesh) {
+                Some(buffers) => self.meshes.insert(pos, buffers),
+                None => self.meshes.remove(&pos),
+            };
+        }
+    }
+
+    /// Loads and unloads chunks around the camera and hands out jobs.
+    /// Returns the meshes finished since the last update.
+    fn stream(&mut self, eye: uv::Vec3) -> Vec<(ChunkPos, ChunkMesh)> {
+        let center = ChunkPos::from_block_pos(uv::IVec3::new(
+            eye.x.floor() as i32,
+            0,
+            eye.z.floor() as i32,
+        ));
+        let load_distance = self.settings.view_distance;
+        let unload_distance = load_distance + self.settings.unload_margin.max(0);
+        let within = move |pos: ChunkPos, distance: i32| {
+            let (dx, dz) = (pos.x - center.x, pos.z - center.z);
+            dx * dx + dz * dz <= distance * distance
+        };
+
+        // Chunks that have left the area aren't worth generating. Chunks that
+        // are still loaded carry on meshing, or they'd be left without an up
+        // to date mesh if the camera came back before they were unloaded.
+        self.workers.set_focus(eye);
+        self.workers.cancel_where(|pos, kind| {
+            kind == ChunkJobKind::Generate && !within(pos, load_distance)
+        });
+        self.unload_where(|pos| !within(pos, unload_distance));
+
+        for dz in -load_distance..=load_distance {
+            for dx in -load_distance..=load_distance {
+                let pos = center.offset(dx, dz);
+                if within(pos, load_distance)
+                    && !self.world.is_chunk_loaded(pos)
+                    && !self.generated.contains_key(&pos)
+                    && !self.workers.is_pending(pos, ChunkJobKind::Generate)
+                {
+                    self.workers.generate(pos);
+                }
+            }
+        }
+
+        let mut meshes = Vec::new();
+        for output in self.workers.receive() {
+            match output {
+                ChunkJobOutput::Generated {
+                    pos,
+                    chunk,
+                    overflow,
+                } => {
+                    self.generated
+                        .insert(pos, GeneratedChunk { chunk, overflow });
+                }
+                ChunkJobOutput::Meshed { pos, mesh } => meshes.push((pos, mesh)),
+            }
+        }
+        self.add_generated_chunks(center);
+
+        for pos in self.needs_mesh.drain() {
+            if let Some(snapshot) = NeighbourhoodSnapshot::from_world(&self.world, pos) {
+                self.workers.mesh(snapshot);
+            }
+        }
+        meshes
+    }
+
+    /// Adds the generated chunks nearest to `center` to the world, up to the
+    /// limit for a frame, spreading light between them and their neighbours.
+    fn add_generated_chunks(&mut self, center: ChunkPos) {
+        let mut waiting: Vec<ChunkPos> = self.generated.keys().copied().collect();
+        waiting.sort_by_key(|pos| {
+            let (dx, dz) = (pos.x - center.x, pos.z - center.z);
+            dx * dx + dz * dz
+        });
+
+        for pos in waiting
+            .into_iter()
+            .take(self.settings.chunks_added_per_frame)
+        {
+            let GeneratedChunk { chunk, overflow } = self.generated.remove(&pos).unwrap();
+            let written = self.world.insert_generated_chunk(pos, chunk, overflow);
+            let mut changed = self.lighting.stitch_chunk(&mut self.world, pos);
+            for block_pos in written {
+                changed.extend(self.lighting.update_block(&mut self.world, block_pos));
+                changed.insert(ChunkPos::from_block_pos(block_pos));
+            }
+
+            // Neighbours' edges may have been drawn against a missing chunk,
+            // so they're meshed again too.
+            self.mark_for_meshing(pos);
+            for changed_pos in changed {
+                self.mark_for_meshing(changed_pos);
+            }
+        }
+    }
+
+    /// Marks a chunk for meshing, along with its neighbours, whose edges
+    /// depend on its blocks and light.
+    fn mark_for_meshing(&mut self, pos: ChunkPos) {
+        for dz in -1..=1 {
+            for dx in -1..=1 {
+                let neighbour = pos.offset(dx, dz);
+                if self.world.is_chunk_loaded(neighbour) {
+                    self.needs_mesh.insert(neighbour);
+                }
+            }
+        }
+    }
+
+    /// Unloads chunks and frees their GPU buffers. Generated chunks that
+    /// haven't been added to the world yet are dropped too.
+    fn unload_where(&mut self, mut predicate: impl FnMut(ChunkPos) -> bool) {
+        self.generated.retain(|pos, _| !predicate(*pos));
+        let unloading: Vec<ChunkPos> = self
+            .world
+            .chunks()
+            .map(|(pos, _)| *pos)
+            .filter(|pos| predicate(*pos))
+            .collect();
+        if unloading.is_empty() {
+            return;
+        }
+
+        for pos in &unloading {
+            self.world.remove_chunk(*pos);
+            self.needs_mesh.remove(pos);
+            if let Some(buffers) = self.meshes.remove(pos) {
+                buffers.vertex_buffer.destroy();
+                buffers.index_buffer.destroy();
+            }
+        }
+        self.workers.cancel_where(|pos, _| unloading.contains(&pos));
+        debug!("Unloaded {} chunks", unloading.len());
+    }
+
+    /// Draws every meshed chunk. The pipeline and bind groups must already be
+    /// set.
+    pub fn draw<'a>(&'a self, render_pass: &mut RenderPass<'a>) {
+        for buffers in self.meshes.values() {
+            render_pass.set_vertex_buffer(0, buffers.vertex_buffer.slice(..));
+            render_pass.set_index_buffer(buffers.index_buffer.slice(..), wgpu::IndexFormat::Uint32);
+            render_pass.draw_indexed(0..buffers.index_count, 0, 0..1);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        collections::HashSet,
+        thread,
+        time::{Duration, Instant},
+    };
+
+    use crate::{
+        jobs::chunk_workers::ChunkBuilder,
+        meshing::mesher::{
+            tests::{mesher, registry, DIRT, STONE},
+            MeshingMode,
+        },
+        worldgen::flat::FlatGenerator,
+    };
+
+    use super::*;
+
+    const HOME: ChunkPos = ChunkPos { x: 0, z: 0 };
+    /// Far enough from `HOME` that some chunks around it are out of view but
+    /// not yet unloaded.
+    const AWAY: ChunkPos = ChunkPos { x: 3, z: 0 };
+
+    fn chunk_manager() -> ChunkManager {
+        let lighting = Lighting::from_registry(&registry());
+        let builder = ChunkBuilder {
+            generator: Box::new(FlatGenerator::new(vec![STONE, DIRT])),
+            decorator: None,
+            lighting: lighting.clone(),
+            mesher: mesher(MeshingMode::Greedy),
+        };
+        let settings = StreamingSettings {
+            view_distance: 2,
+            unload_margin: 2,
+            chunks_added_per_frame: usize::MAX,
+        };
+        ChunkManager::new(ChunkWorkers::new(builder, 1), lighting, settings)
+    }
+
+    fn eye_over(pos: ChunkPos) -> uv::Vec3 {
+        let origin = pos.origin();
+        uv::Vec3::new(origin.x as f32 + 8.0, 100.0, origin.z as f32 + 8.0)
+    }
+
+    /// Streams around a chunk until every job has finished, returning the
+    /// chunks meshed along the way.
+    fn settle(manager: &mut ChunkManager, pos: ChunkPos) -> HashSet<ChunkPos> {
+        let deadline = Instant::now() + Duration::from_secs(30);
+        let mut meshed = HashSet::new();
+        loop {
+            meshed.extend(
+                manager
+                    .stream(eye_over(pos))
+                    .into_iter()
+                    .map(|(pos, _)| pos),
+            );
+            if manager.workers.pending_count() == 0 && manager.generated.is_empty() {
+                return meshed;
+            }
+            assert!(Instant::now() < deadline, "chunks took too long to stream");
+            thread::sleep(Duration::from_millis(1));
+        }
+    }
+
+    #[test]
+    fn chunks_within_the_unload_margin_stay_loaded() {
+        let mut manager = chunk_manager();
+        settle(&mut manager, HOME);
+        assert!(manager.world.is_chunk_loaded(ChunkPos::new(-2, 0)));
+
+        settle(&mut manager, AWAY);
+        assert!(manager.world.is_chunk_loaded(ChunkPos::new(-1, 0)));
+        assert!(!manager.world.is_chunk_loaded(ChunkPos::new(-2, 0)));
+        assert!(manager.world.is_chunk_loaded(ChunkPos::new(5, 0)));
+    }
+
+    #[test]
+    fn chunks_left_loaded_are_meshed_when_the_camera_comes_back() {
+        let mut manager = chunk_manager();
+        settle(&mut manager, HOME);
+
+        // Mesh everything again, then move away before the meshing is done.
+        let mut settings = manager.mesher_settings();
+        settings.mode = settings.mode.toggled();
+        manager.set_mesher_settings(settings);
+        let mut meshed: HashSet<ChunkPos> = manager
+            .stream(eye_over(HOME))
+            .into_iter()
+            .map(|(pos, _)| pos)
+            .collect();
+        meshed.extend(settle(&mut manager, AWAY));
+        meshed.extend(settle(&mut manager, HOME));
+
+        for (pos, _) in manager.world.chunks() {
+            assert!(meshed.contains(pos), "chunk {:?} was never meshed", pos);
+        }
+    }
+}
@@ -1,15 +1,18 @@
 use wgpu::{Device, Surface, SurfaceConfiguration};
-use winit::{event::WindowEvent, event_loop::ControlFlow, window::Window};
+use winit::{
+    event::{DeviceEvent, ElementState, WindowEvent},
+    event_loop::ControlFlow,
+};
 
-use crate::input::InputManager;
+use crate::{depth_texture::DepthTexture, input::input_manager::InputManager};
 
 pub fn handle_window_event(
     event: WindowEvent<'_>,
-    window: &Window,
     device: &Device,
     control_flow: &mut ControlFlow,
     surface: &Surface,
     config: &mut SurfaceConfiguration,
+    depth_texture: &mut DepthTexture,
     input_manager: &mut InputManager,
 ) {
     match event {
@@ -17,27 +20,39 @@ pub fn handle_window_event(
         WindowEvent::Resized(dimensions) => {
             config.width = dimensions.width;
             config.height = dimensions.height;
-            surface.configure(&device, &config);
+            surface.configure(device, config);
+            depth_texture.resize(device, config);
         }
         WindowEvent::ScaleFactorChanged { new_inner_size, .. } => {
             config.width = new_inner_size.width;
             config.height = new_inner_size.height;
-            surface.configure(&device, &config);
+            surface.configure(device, config);
+            depth_texture.resize(device, config);
         }
         WindowEvent::KeyboardInput { input, .. } => {
             if let Some(key) = input.virtual_keycode {
-                input_manager.set_key_state(key, input.state == winit::event::ElementState::Pressed)
-            }
-
-            if input.state == winit::event::ElementState::Released {
-                match input.virtual_keycode {
-                    Some(winit::event::VirtualKeyCode::F11) => {
-                        super::window::toggle_fullscreen(&window);
-                    }
-                    _ => (),
-                }
+                input_manager.set_key_state(key, input.state == ElementState::Pressed);
             }
         }
+        WindowEvent::MouseInput { state, button, .. } => {
+            input_manager.set_mouse_button_state(button, state == ElementState::Pressed);
+        }
+        WindowEvent::ModifiersChanged(modifiers) => input_manager.set_modifiers(modifiers.into()),
+        WindowEvent::CursorMoved { position, .. } => {
+            input_manager.set_cursor_position(Some((position.x, position.y)))
+        }
+        WindowEvent::CursorLeft { .. } => input_manager.set_cursor_position(None),
+        WindowEvent::MouseWheel { delta, .. } => input_manager.add_scroll(delta),
+        // Anything released while the window is in the background would
+        // otherwise stay held down.
+        WindowEvent::Focused(false) => input_manager.release_all(),
         _ => (),
     }
 }
+
+/// Handles raw input from devices, which arrives whichever window has focus.
+pub fn handle_device_event(event: DeviceEvent, input_manager: &mut InputManager) {
+    if let DeviceEvent::MouseMotion { delta } = event {
+        input_manager.add_mouse_motion(delta);
+    }
+}
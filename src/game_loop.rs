@@ -0,0 +1,225 @@
+use std::{
+    cell::Cell,
+    rc::Rc,
+    time::{Duration, Instant},
+};
+
+/// A source of time for the game loop.
+pub trait Clock {
+    /// Time passed since some fixed starting point.
+    fn now(&self) -> Duration;
+}
+
+/// Real time, measured from when the clock was created.
+pub struct SystemClock {
+    start: Instant,
+}
+
+impl SystemClock {
+    pub fn new() -> Self {
+        Self {
+            start: Instant::now(),
+        }
+    }
+}
+
+impl Default for SystemClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Clock for SystemClock {
+    fn now(&self) -> Duration {
+        self.start.elapsed()
+    }
+}
+
+/// A clock that only moves when told to, so the game loop can be stepped
+/// through exactly.
+///
+/// Clones share the same time, so one can be handed to a `FixedTimestep`
+/// while another is kept to drive it.
+#[derive(Debug, Default, Clone)]
+pub struct ManualClock {
+    now: Rc<Cell<Duration>>,
+}
+
+impl ManualClock {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn advance(&self, duration: Duration) {
+        self.now.set(self.now.get() + duration);
+    }
+
+    pub fn set(&self, now: Duration) {
+        self.now.set(now);
+    }
+}
+
+impl Clock for ManualClock {
+    fn now(&self) -> Duration {
+        self.now.get()
+    }
+}
+
+/// Length of a simulation step at `ticks_per_second`. Anything stepping the
+/// simulation has to use exactly this, so that replays match.
+pub fn step_length(ticks_per_second: u32) -> Duration {
+    Duration::from_secs(1) / ticks_per_second.max(1)
+}
+
+/// What to do for one rendered frame.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Frame {
+    /// Simulation steps to run before rendering.
+    pub ticks: u32,
+    /// How far between the last two ticks the frame falls, from 0 to 1, for
+    /// interpolating what's drawn.
+    pub alpha: f32,
+}
+
+/// Runs the simulation in fixed steps, however fast frames are rendered.
+///
+/// Time passing between frames builds up in an accumulator, and each step
+/// takes its length back out of it. Whatever's left over is less than a
+/// step, and becomes the frame's interpolation alpha.
+pub struct FixedTimestep<C: Clock> {
+    clock: C,
+    step: Duration,
+    /// Longest time a single frame can add, so that a long stall, like a
+    /// window being dragged, doesn't leave a backlog of steps that takes even
+    /// longer to catch up on.
+    max_frame_time: Duration,
+    accumulator: Duration,
+    last_time: Duration,
+    tick: u64,
+}
+
+impl<C: Clock> FixedTimestep<C> {
+    /// Steps `ticks_per_second` times a second, starting from the clock's
+    /// current time.
+    pub fn new(clock: C, ticks_per_second: u32) -> Self {
+        let last_time = clock.now();
+        Self {
+            clock,
+            step: step_length(ticks_per_second),
+            max_frame_time: Duration::from_millis(250),
+            accumulator: Duration::ZERO,
+            last_time,
+            tick: 0,
+        }
+    }
+
+    /// Length of a simulation step, in seconds.
+    pub fn step_seconds(&self) -> f32 {
+        self.step.as_secs_f32()
+    }
+
+    /// Steps run so far.
+    pub fn tick(&self) -> u64 {
+        self.tick
+    }
+
+    /// Works out how many steps are due since the last frame.
+    pub fn advance(&mut self) -> Frame {
+        let now = self.clock.now();
+        let elapsed = now.saturating_sub(self.last_time);
+        self.last_time = now;
+        self.accumulator += elapsed.min(self.max_frame_time);
+
+        let mut ticks = 0;
+        while self.accumulator >= self.step {
+            self.accumulator -= self.step;
+            ticks += 1;
+        }
+        self.tick += ticks as u64;
+
+        Frame {
+            ticks,
+            alpha: self.accumulator.as_secs_f32() / self.step.as_secs_f32(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 20ms steps.
+    const TICKS_PER_SECOND: u32 = 50;
+
+    fn timestep() -> (ManualClock, FixedTimestep<ManualClock>) {
+        let clock = ManualClock::new();
+        let timestep = FixedTimestep::new(clock.clone(), TICKS_PER_SECOND);
+        (clock, timestep)
+    }
+
+    fn advance(
+        clock: &ManualClock,
+        timestep: &mut FixedTimestep<ManualClock>,
+        millis: u64,
+    ) -> Frame {
+        clock.advance(Duration::from_millis(millis));
+        timestep.advance()
+    }
+
+    fn assert_frame(frame: Frame, ticks: u32, alpha: f32) {
+        assert_eq!(frame.ticks, ticks);
+        assert!(
+            (frame.alpha - alpha).abs() < 1e-5,
+            "alpha was {}, expected {}",
+            frame.alpha,
+            alpha
+        );
+    }
+
+    #[test]
+    fn frames_shorter_than_a_step_only_move_alpha() {
+        let (clock, mut timestep) = timestep();
+        assert_frame(timestep.advance(), 0, 0.0);
+        assert_frame(advance(&clock, &mut timestep, 10), 0, 0.5);
+        assert_frame(advance(&clock, &mut timestep, 5), 0, 0.75);
+        assert_frame(advance(&clock, &mut timestep, 5), 1, 0.0);
+        assert_eq!(timestep.tick(), 1);
+    }
+
+    #[test]
+    fn long_frames_run_several_ticks() {
+        let (clock, mut timestep) = timestep();
+        assert_frame(advance(&clock, &mut timestep, 65), 3, 0.25);
+        assert_eq!(timestep.tick(), 3);
+    }
+
+    #[test]
+    fn time_left_over_carries_into_the_next_frame() {
+        let (clock, mut timestep) = timestep();
+        let frames: Vec<Frame> = (0..4).map(|_| advance(&clock, &mut timestep, 15)).collect();
+        assert_frame(frames[0], 0, 0.75);
+        assert_frame(frames[1], 1, 0.5);
+        assert_frame(frames[2], 1, 0.25);
+        assert_frame(frames[3], 1, 0.0);
+        assert_eq!(timestep.tick(), 3);
+    }
+
+    #[test]
+    fn stalls_are_clamped_to_the_max_frame_time() {
+        let (clock, mut timestep) = timestep();
+        // 250ms is twelve and a half steps.
+        assert_frame(advance(&clock, &mut timestep, 5000), 12, 0.5);
+        assert_frame(advance(&clock, &mut timestep, 10), 1, 0.0);
+        assert_eq!(timestep.tick(), 13);
+    }
+
+    #[test]
+    fn a_clock_going_backwards_runs_no_ticks() {
+        let (clock, mut timestep) = timestep();
+        assert_frame(advance(&clock, &mut timestep, 110), 5, 0.5);
+        clock.set(Duration::from_millis(50));
+        assert_frame(timestep.advance(), 0, 0.5);
+        // Time is counted from where the clock went back to.
+        assert_frame(advance(&clock, &mut timestep, 10), 1, 0.0);
+    }
+}
@@ -0,0 +1,55 @@
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+/// Something the player can do, which bindings map keys, mouse buttons and
+/// gamepad inputs to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum Action {
+    MoveForward,
+    MoveBackward,
+    MoveLeft,
+    MoveRight,
+    MoveUp,
+    MoveDown,
+    LookLeft,
+    LookRight,
+    LookUp,
+    LookDown,
+    ToggleFullscreen,
+    ToggleCursorGrab,
+    SwitchCamera,
+    IncreaseViewDistance,
+    DecreaseViewDistance,
+    ToggleAmbientOcclusion,
+    ToggleMeshingMode,
+}
+
+impl Action {
+    pub const ALL: [Action; 17] = [
+        Action::MoveForward,
+        Action::MoveBackward,
+        Action::MoveLeft,
+        Action::MoveRight,
+        Action::MoveUp,
+        Action::MoveDown,
+        Action::LookLeft,
+        Action::LookRight,
+        Action::LookUp,
+        Action::LookDown,
+        Action::ToggleFullscreen,
+        Action::ToggleCursorGrab,
+        Action::SwitchCamera,
+        Action::IncreaseViewDistance,
+        Action::DecreaseViewDistance,
+        Action::ToggleAmbientOcclusion,
+        Action::ToggleMeshingMode,
+    ];
+}
+
+impl fmt::Display for Action {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // Matches the names used in the bindings file.
+        fmt::Debug::fmt(self, f)
+    }
+}
@@ -0,0 +1,418 @@
+use std::{collections::BTreeMap, fmt, fs};
+
+use log::info;
+use serde::{Deserialize, Serialize};
+use winit::event::{ModifiersState, MouseButton, VirtualKeyCode};
+
+use super::{
+    action::Action,
+    gamepad::{AxisDirection, GamepadAxis, GamepadButton},
+};
+
+/// A key, mouse button or gamepad input that can be bound to an action.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Trigger {
+    Key(VirtualKeyCode),
+    Mouse(MouseButton),
+    GamepadButton(GamepadButton),
+    /// A stick or trigger pushed in one direction. Counts as pressed once
+    /// it's pushed past halfway, but also gives how far it's pushed.
+    GamepadAxis(GamepadAxis, AxisDirection),
+}
+
+impl Trigger {
+    pub fn is_gamepad(&self) -> bool {
+        matches!(self, Trigger::GamepadButton(_) | Trigger::GamepadAxis(..))
+    }
+}
+
+/// Modifier keys that have to be held for a binding to apply.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Modifiers {
+    pub shift: bool,
+    pub ctrl: bool,
+    pub alt: bool,
+    pub logo: bool,
+}
+
+impl Modifiers {
+    pub const NONE: Modifiers = Modifiers {
+        shift: false,
+        ctrl: false,
+        alt: false,
+        logo: false,
+    };
+
+    pub fn is_none(&self) -> bool {
+        *self == Self::NONE
+    }
+
+    /// Whether every modifier in `self` is also held in `held`.
+    pub fn is_subset_of(&self, held: Modifiers) -> bool {
+        (!self.shift || held.shift)
+            && (!self.ctrl || held.ctrl)
+            && (!self.alt || held.alt)
+            && (!self.logo || held.logo)
+    }
+
+    fn count(&self) -> u32 {
+        self.shift as u32 + self.ctrl as u32 + self.alt as u32 + self.logo as u32
+    }
+}
+
+impl From<ModifiersState> for Modifiers {
+    fn from(state: ModifiersState) -> Self {
+        Self {
+            shift: state.shift(),
+            ctrl: state.ctrl(),
+            alt: state.alt(),
+            logo: state.logo(),
+        }
+    }
+}
+
+/// A trigger, along with the modifiers held with it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Binding {
+    pub trigger: Trigger,
+    #[serde(default, skip_serializing_if = "Modifiers::is_none")]
+    pub modifiers: Modifiers,
+}
+
+impl Binding {
+    pub fn key(key: VirtualKeyCode) -> Self {
+        Self {
+            trigger: Trigger::Key(key),
+            modifiers: Modifiers::NONE,
+        }
+    }
+
+    pub fn mouse(button: MouseButton) -> Self {
+        Self {
+            trigger: Trigger::Mouse(button),
+            modifiers: Modifiers::NONE,
+        }
+    }
+
+    pub fn with_modifiers(self, modifiers: Modifiers) -> Self {
+        Self { modifiers, ..self }
+    }
+}
+
+impl fmt::Display for Binding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let names = [
+            (self.modifiers.ctrl, "Ctrl"),
+            (self.modifiers.alt, "Alt"),
+            (self.modifiers.shift, "Shift"),
+            (self.modifiers.logo, "Logo"),
+        ];
+        for (_, name) in names.iter().filter(|(held, _)| *held) {
+            write!(f, "{}+", name)?;
+        }
+        match self.trigger {
+            Trigger::Key(key) => write!(f, "{:?}", key),
+            Trigger::Mouse(MouseButton::Other(button)) => write!(f, "Mouse{}", button),
+            Trigger::Mouse(button) => write!(f, "{:?}Mouse", button),
+            Trigger::GamepadButton(button) => write!(f, "Gamepad{:?}", button),
+            Trigger::GamepadAxis(axis, AxisDirection::Positive) => write!(f, "{:?}+", axis),
+            Trigger::GamepadAxis(axis, AxisDirection::Negative) => write!(f, "{:?}-", axis),
+        }
+    }
+}
+
+/// Maps actions to the keys, mouse buttons and gamepad inputs that perform
+/// them.
+///
+/// Each binding performs at most one action, so bindings never conflict.
+#[derive(Debug, Clone, Default)]
+pub struct Bindings {
+    // Ordered so that saved files come out the same every time.
+    bindings: BTreeMap<Action, Vec<Binding>>,
+}
+
+impl Bindings {
+    /// Loads bindings from a RON file mapping each action to a list of
+    /// bindings.
+    pub fn load(path: &str) -> Result<Self, BindingsError> {
+        info!("Loading key bindings from {}", path);
+        let source = fs::read_to_string(path).map_err(|error| BindingsError::Io {
+            path: path.to_string(),
+            error,
+        })?;
+        let bindings: BTreeMap<Action, Vec<Binding>> =
+            ron::from_str(&source).map_err(|error| BindingsError::Parse {
+                path: path.to_string(),
+                error,
+            })?;
+        Self::from_map(bindings)
+    }
+
+    /// Builds bindings from a map, failing if any binding is used by more
+    /// than one action.
+    pub fn from_map(map: BTreeMap<Action, Vec<Binding>>) -> Result<Self, BindingsError> {
+        let mut bindings = Self::default();
+        for (action, action_bindings) in map {
+            for binding in action_bindings {
+                bindings.bind(action, binding)?;
+            }
+        }
+        Ok(bindings)
+    }
+
+    /// Writes the bindings to a RON file that `load` can read back.
+    pub fn save(&self, path: &str) -> Result<(), BindingsError> {
+        let source = ron::ser::to_string_pretty(&self.bindings, ron::ser::PrettyConfig::default())
+            .map_err(|error| BindingsError::Serialize {
+                path: path.to_string(),
+                error,
+            })?;
+        fs::write(path, source).map_err(|error| BindingsError::Io {
+            path: path.to_string(),
+            error,
+        })
+    }
+
+    /// Adds a binding to an action, keeping the action's other bindings.
+    pub fn bind(&mut self, action: Action, binding: Binding) -> Result<(), BindingsError> {
+        if let Some(existing) = self.action_for(binding) {
+            if existing == action {
+                return Ok(());
+            }
+            return Err(BindingsError::Conflict {
+                binding,
+                first: existing,
+                second: action,
+            });
+        }
+        self.bindings.entry(action).or_default().push(binding);
+        Ok(())
+    }
+
+    /// Replaces every binding of an action with a single new one.
+    ///
+    /// Fails without changing anything if the binding is already used by
+    /// another action.
+    pub fn rebind(&mut self, action: Action, binding: Binding) -> Result<(), BindingsError> {
+        match self.action_for(binding) {
+            Some(existing) if existing != action => Err(BindingsError::Conflict {
+                binding,
+                first: existing,
+                second: action,
+            }),
+            _ => {
+                info!("Bound {} to {}", action, binding);
+                self.bindings.insert(action, vec![binding]);
+                Ok(())
+            }
+        }
+    }
+
+    /// Removes a binding, returning the action it was bound to.
+    pub fn unbind(&mut self, binding: Binding) -> Option<Action> {
+        let action = self.action_for(binding)?;
+        if let Some(action_bindings) = self.bindings.get_mut(&action) {
+            action_bindings.retain(|&existing| existing != binding);
+        }
+        Some(action)
+    }
+
+    /// The action a binding performs, if it's bound.
+    pub fn action_for(&self, binding: Binding) -> Option<Action> {
+        self.bindings
+            .iter()
+            .find(|(_, action_bindings)| action_bindings.contains(&binding))
+            .map(|(&action, _)| action)
+    }
+
+    pub fn bindings_for(&self, action: Action) -> &[Binding] {
+        self.bindings.get(&action).map_or(&[], Vec::as_slice)
+    }
+
+    /// Whether an action is performed by its trigger with these modifiers
+    /// held.
+    ///
+    /// When a trigger has several bindings, the one needing the most of the
+    /// held modifiers wins, so Ctrl+S doesn't also count as S.
+    pub fn matches(&self, action: Action, trigger: Trigger, modifiers: Modifiers) -> bool {
+        let best = self
+            .bindings
+            .values()
+            .flatten()
+            .filter(|binding| {
+                binding.trigger == trigger && binding.modifiers.is_subset_of(modifiers)
+            })
+            .max_by_key(|binding| binding.modifiers.count());
+        best.is_some_and(|&binding| self.action_for(binding) == Some(action))
+    }
+}
+
+#[derive(Debug)]
+pub enum BindingsError {
+    Io {
+        path: String,
+        error: std::io::Error,
+    },
+    Parse {
+        path: String,
+        error: ron::error::SpannedError,
+    },
+    Serialize {
+        path: String,
+        error: ron::Error,
+    },
+    Conflict {
+        binding: Binding,
+        first: Action,
+        second: Action,
+    },
+}
+
+impl fmt::Display for BindingsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BindingsError::Io { path, error } => {
+                write!(f, "failed to access key bindings at {}: {}", path, error)
+            }
+            BindingsError::Parse { path, error } => {
+                write!(f, "failed to parse key bindings at {}: {}", path, error)
+            }
+            BindingsError::Serialize { path, error } => {
+                write!(f, "failed to write key bindings to {}: {}", path, error)
+            }
+            BindingsError::Conflict {
+                binding,
+                first,
+                second,
+            } => write!(
+                f,
+                "{} can't be bound to {} because it's already bound to {}",
+                binding, second, first
+            ),
+        }
+    }
+}
+
+impl std::error::Error for BindingsError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            BindingsError::Io { error, .. } => Some(error),
+            BindingsError::Parse { error, .. } => Some(error),
+            BindingsError::Serialize { error, .. } => Some(error),
+            BindingsError::Conflict { .. } => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CTRL: Modifiers = Modifiers {
+        ctrl: true,
+        ..Modifiers::NONE
+    };
+
+    fn bindings() -> Bindings {
+        let mut bindings = Bindings::default();
+        bindings
+            .bind(Action::MoveBackward, Binding::key(VirtualKeyCode::S))
+            .unwrap();
+        bindings
+            .bind(
+                Action::ToggleFullscreen,
+                Binding::key(VirtualKeyCode::S).with_modifiers(CTRL),
+            )
+            .unwrap();
+        bindings
+            .bind(Action::MoveForward, Binding::key(VirtualKeyCode::W))
+            .unwrap();
+        bindings
+    }
+
+    fn assert_unchanged(bindings: &Bindings) {
+        assert_eq!(
+            bindings.bindings_for(Action::MoveBackward),
+            [Binding::key(VirtualKeyCode::S)]
+        );
+        assert_eq!(
+            bindings.bindings_for(Action::MoveForward),
+            [Binding::key(VirtualKeyCode::W)]
+        );
+    }
+
+    #[test]
+    fn binding_a_used_binding_fails_without_changing_anything() {
+        let mut bindings = bindings();
+        let result = bindings.bind(Action::MoveForward, Binding::key(VirtualKeyCode::S));
+        assert!(matches!(
+            result,
+            Err(BindingsError::Conflict {
+                first: Action::MoveBackward,
+                second: Action::MoveForward,
+                ..
+            })
+        ));
+        assert_unchanged(&bindings);
+    }
+
+    #[test]
+    fn rebinding_to_a_used_binding_fails_without_changing_anything() {
+        let mut bindings = bindings();
+        let result = bindings.rebind(Action::MoveForward, Binding::key(VirtualKeyCode::S));
+        assert!(matches!(
+            result,
+            Err(BindingsError::Conflict {
+                first: Action::MoveBackward,
+                second: Action::MoveForward,
+                ..
+            })
+        ));
+        assert_unchanged(&bindings);
+    }
+
+    #[test]
+    fn rebinding_replaces_the_action_bindings() {
+        let mut bindings = bindings();
+        bindings
+            .rebind(Action::MoveForward, Binding::key(VirtualKeyCode::Up))
+            .unwrap();
+        assert_eq!(
+            bindings.bindings_for(Action::MoveForward),
+            [Binding::key(VirtualKeyCode::Up)]
+        );
+        assert_eq!(bindings.action_for(Binding::key(VirtualKeyCode::W)), None);
+    }
+
+    #[test]
+    fn unbinding_removes_the_action() {
+        let mut bindings = bindings();
+        assert_eq!(
+            bindings.unbind(Binding::key(VirtualKeyCode::W)),
+            Some(Action::MoveForward)
+        );
+        assert_eq!(bindings.action_for(Binding::key(VirtualKeyCode::W)), None);
+        assert!(bindings.bindings_for(Action::MoveForward).is_empty());
+        assert_eq!(bindings.unbind(Binding::key(VirtualKeyCode::W)), None);
+    }
+
+    #[test]
+    fn held_modifiers_pick_the_binding_needing_the_most_of_them() {
+        let bindings = bindings();
+        let s = Trigger::Key(VirtualKeyCode::S);
+
+        assert!(bindings.matches(Action::ToggleFullscreen, s, CTRL));
+        assert!(!bindings.matches(Action::MoveBackward, s, CTRL));
+
+        assert!(bindings.matches(Action::MoveBackward, s, Modifiers::NONE));
+        assert!(!bindings.matches(Action::ToggleFullscreen, s, Modifiers::NONE));
+
+        // Modifiers no binding needs don't stop one matching.
+        let shift = Modifiers {
+            shift: true,
+            ..Modifiers::NONE
+        };
+        assert!(bindings.matches(Action::MoveBackward, s, shift));
+    }
+}
@@ -0,0 +1,246 @@
+use std::{
+    cell::RefCell,
+    collections::{HashMap, HashSet, VecDeque},
+    rc::Rc,
+};
+
+use serde::{Deserialize, Serialize};
+
+/// Identifies a connected gamepad. A gamepad that's unplugged and plugged
+/// back in may or may not get the same id.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct GamepadId(pub usize);
+
+/// Buttons named by their position on the gamepad, so that the same
+/// bindings work whatever's printed on them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum GamepadButton {
+    South,
+    East,
+    North,
+    West,
+    LeftBumper,
+    RightBumper,
+    Select,
+    Start,
+    Mode,
+    LeftStick,
+    RightStick,
+    DPadUp,
+    DPadDown,
+    DPadLeft,
+    DPadRight,
+}
+
+impl GamepadButton {
+    pub const ALL: [GamepadButton; 15] = [
+        GamepadButton::South,
+        GamepadButton::East,
+        GamepadButton::North,
+        GamepadButton::West,
+        GamepadButton::LeftBumper,
+        GamepadButton::RightBumper,
+        GamepadButton::Select,
+        GamepadButton::Start,
+        GamepadButton::Mode,
+        GamepadButton::LeftStick,
+        GamepadButton::RightStick,
+        GamepadButton::DPadUp,
+        GamepadButton::DPadDown,
+        GamepadButton::DPadLeft,
+        GamepadButton::DPadRight,
+    ];
+}
+
+/// Analogue inputs. Sticks go from -1 to 1, positive being right and up, and
+/// triggers go from 0 when let go to 1 when pulled all the way.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum GamepadAxis {
+    LeftStickX,
+    LeftStickY,
+    RightStickX,
+    RightStickY,
+    LeftTrigger,
+    RightTrigger,
+}
+
+impl GamepadAxis {
+    pub const ALL: [GamepadAxis; 6] = [
+        GamepadAxis::LeftStickX,
+        GamepadAxis::LeftStickY,
+        GamepadAxis::RightStickX,
+        GamepadAxis::RightStickY,
+        GamepadAxis::LeftTrigger,
+        GamepadAxis::RightTrigger,
+    ];
+
+    /// The other axis of the same stick, or None for triggers.
+    pub fn stick_partner(self) -> Option<GamepadAxis> {
+        match self {
+            GamepadAxis::LeftStickX => Some(GamepadAxis::LeftStickY),
+            GamepadAxis::LeftStickY => Some(GamepadAxis::LeftStickX),
+            GamepadAxis::RightStickX => Some(GamepadAxis::RightStickY),
+            GamepadAxis::RightStickY => Some(GamepadAxis::RightStickX),
+            GamepadAxis::LeftTrigger | GamepadAxis::RightTrigger => None,
+        }
+    }
+}
+
+/// Which way an axis has to be pushed for a binding to apply.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum AxisDirection {
+    Positive,
+    Negative,
+}
+
+impl AxisDirection {
+    pub fn sign(self) -> f32 {
+        match self {
+            AxisDirection::Positive => 1.0,
+            AxisDirection::Negative => -1.0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum GamepadEvent {
+    Connected(GamepadId),
+    Disconnected(GamepadId),
+    Button {
+        id: GamepadId,
+        button: GamepadButton,
+        pressed: bool,
+    },
+    /// An axis moved to a new raw value, before dead zones are applied.
+    Axis {
+        id: GamepadId,
+        axis: GamepadAxis,
+        value: f32,
+    },
+}
+
+/// A source of gamepad events, such as a platform gamepad library.
+pub trait GamepadBackend {
+    /// The next event that hasn't been handled yet, or None once they've all
+    /// been handled. Never blocks.
+    fn next_event(&mut self) -> Option<GamepadEvent>;
+}
+
+/// How far sticks and triggers have to move before they count, as a
+/// fraction of their full range.
+///
+/// Worn sticks rarely sit exactly in the middle, so without a dead zone the
+/// camera would drift on its own.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DeadZones {
+    pub stick: f32,
+    pub trigger: f32,
+}
+
+impl Default for DeadZones {
+    fn default() -> Self {
+        Self {
+            stick: 0.15,
+            trigger: 0.05,
+        }
+    }
+}
+
+/// Scales a magnitude so the edge of the dead zone is 0 and full range is
+/// still 1, avoiding a jump as it leaves the dead zone.
+fn rescale(magnitude: f32, dead_zone: f32) -> f32 {
+    if magnitude <= dead_zone {
+        return 0.0;
+    }
+    ((magnitude - dead_zone) / (1.0 - dead_zone)).min(1.0)
+}
+
+/// What a single gamepad is holding down.
+#[derive(Debug, Default, Clone)]
+pub struct GamepadState {
+    buttons: HashSet<GamepadButton>,
+    axes: HashMap<GamepadAxis, f32>,
+}
+
+impl GamepadState {
+    pub fn button_pressed(&self, button: GamepadButton) -> bool {
+        self.buttons.contains(&button)
+    }
+
+    pub fn set_button(&mut self, button: GamepadButton, pressed: bool) {
+        if pressed {
+            self.buttons.insert(button);
+        } else {
+            self.buttons.remove(&button);
+        }
+    }
+
+    pub fn set_axis(&mut self, axis: GamepadAxis, value: f32) {
+        self.axes.insert(axis, value);
+    }
+
+    /// The axis's value with dead zones applied.
+    ///
+    /// Sticks use a dead zone around the centre rather than one along each
+    /// axis, so pushing diagonally isn't snapped to straight lines.
+    pub fn axis(&self, axis: GamepadAxis, dead_zones: DeadZones) -> f32 {
+        let raw = |axis| self.axes.get(&axis).copied().unwrap_or(0.0);
+        match axis.stick_partner() {
+            Some(partner) => {
+                let (value, other) = (raw(axis), raw(partner));
+                let magnitude = (value * value + other * other).sqrt();
+                if magnitude <= dead_zones.stick {
+                    return 0.0;
+                }
+                value / magnitude * rescale(magnitude, dead_zones.stick)
+            }
+            None => rescale(raw(axis).clamp(0.0, 1.0), dead_zones.trigger),
+        }
+    }
+}
+
+/// A gamepad backend driven by hand, for trying out gamepad input without
+/// a gamepad.
+///
+/// Clones share the same events, so one can be handed to an `InputManager`
+/// while another is kept to drive it.
+#[derive(Debug, Default, Clone)]
+pub struct FakeGamepadBackend {
+    events: Rc<RefCell<VecDeque<GamepadEvent>>>,
+}
+
+impl FakeGamepadBackend {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push(&self, event: GamepadEvent) {
+        self.events.borrow_mut().push_back(event);
+    }
+
+    pub fn connect(&self, id: GamepadId) {
+        self.push(GamepadEvent::Connected(id));
+    }
+
+    pub fn disconnect(&self, id: GamepadId) {
+        self.push(GamepadEvent::Disconnected(id));
+    }
+
+    pub fn set_button(&self, id: GamepadId, button: GamepadButton, pressed: bool) {
+        self.push(GamepadEvent::Button {
+            id,
+            button,
+            pressed,
+        });
+    }
+
+    pub fn set_axis(&self, id: GamepadId, axis: GamepadAxis, value: f32) {
+        self.push(GamepadEvent::Axis { id, axis, value });
+    }
+}
+
+impl GamepadBackend for FakeGamepadBackend {
+    fn next_event(&mut self) -> Option<GamepadEvent> {
+        self.events.borrow_mut().pop_front()
+    }
+}
@@ -0,0 +1,114 @@
+use std::collections::VecDeque;
+
+use gilrs::{
+    ev::filter::{axis_dpad_to_button, Filter},
+    Axis, Button, EventType, Gilrs, GilrsBuilder,
+};
+
+use super::gamepad::{GamepadAxis, GamepadBackend, GamepadButton, GamepadEvent, GamepadId};
+
+/// Reads real gamepads through gilrs.
+pub struct GilrsBackend {
+    gilrs: Gilrs,
+    /// Gamepads that were already plugged in, reported before anything else.
+    pending: VecDeque<GamepadEvent>,
+}
+
+impl GilrsBackend {
+    pub fn new() -> Result<Self, Box<gilrs::Error>> {
+        // Dead zones are applied by the input manager, so gilrs is asked for
+        // raw values.
+        let gilrs = GilrsBuilder::new()
+            .with_default_filters(false)
+            .build()
+            .map_err(Box::new)?;
+        let pending = gilrs
+            .gamepads()
+            .map(|(id, _)| GamepadEvent::Connected(GamepadId(id.into())))
+            .collect();
+        Ok(Self { gilrs, pending })
+    }
+}
+
+impl GamepadBackend for GilrsBackend {
+    fn next_event(&mut self) -> Option<GamepadEvent> {
+        if let Some(event) = self.pending.pop_front() {
+            return Some(event);
+        }
+        loop {
+            // Some gamepads report their d-pad as a pair of axes.
+            let event = self
+                .gilrs
+                .next_event()
+                .filter_ev(&axis_dpad_to_button, &mut self.gilrs)?;
+            if let Some(event) = convert_event(GamepadId(event.id.into()), event.event) {
+                return Some(event);
+            }
+        }
+    }
+}
+
+/// Converts an event from gilrs, or returns None for inputs that aren't
+/// used.
+fn convert_event(id: GamepadId, event: EventType) -> Option<GamepadEvent> {
+    match event {
+        EventType::Connected => Some(GamepadEvent::Connected(id)),
+        EventType::Disconnected => Some(GamepadEvent::Disconnected(id)),
+        EventType::ButtonPressed(button, _) | EventType::ButtonReleased(button, _) => {
+            Some(GamepadEvent::Button {
+                id,
+                button: convert_button(button)?,
+                pressed: matches!(event, EventType::ButtonPressed(..)),
+            })
+        }
+        // Analogue triggers are reported as buttons with a value.
+        EventType::ButtonChanged(Button::LeftTrigger2, value, _) => Some(GamepadEvent::Axis {
+            id,
+            axis: GamepadAxis::LeftTrigger,
+            value,
+        }),
+        EventType::ButtonChanged(Button::RightTrigger2, value, _) => Some(GamepadEvent::Axis {
+            id,
+            axis: GamepadAxis::RightTrigger,
+            value,
+        }),
+        EventType::AxisChanged(axis, value, _) => Some(GamepadEvent::Axis {
+            id,
+            axis: convert_axis(axis)?,
+            value,
+        }),
+        _ => None,
+    }
+}
+
+fn convert_button(button: Button) -> Option<GamepadButton> {
+    Some(match button {
+        Button::South => GamepadButton::South,
+        Button::East => GamepadButton::East,
+        Button::North => GamepadButton::North,
+        Button::West => GamepadButton::West,
+        Button::LeftTrigger => GamepadButton::LeftBumper,
+        Button::RightTrigger => GamepadButton::RightBumper,
+        Button::Select => GamepadButton::Select,
+        Button::Start => GamepadButton::Start,
+        Button::Mode => GamepadButton::Mode,
+        Button::LeftThumb => GamepadButton::LeftStick,
+        Button::RightThumb => GamepadButton::RightStick,
+        Button::DPadUp => GamepadButton::DPadUp,
+        Button::DPadDown => GamepadButton::DPadDown,
+        Button::DPadLeft => GamepadButton::DPadLeft,
+        Button::DPadRight => GamepadButton::DPadRight,
+        // The analogue triggers are handled as axes.
+        _ => return None,
+    })
+}
+
+fn convert_axis(axis: Axis) -> Option<GamepadAxis> {
+    match axis {
+        Axis::LeftStickX => Some(GamepadAxis::LeftStickX),
+        Axis::LeftStickY => Some(GamepadAxis::LeftStickY),
+        Axis::RightStickX => Some(GamepadAxis::RightStickX),
+        Axis::RightStickY => Some(GamepadAxis::RightStickY),
+        _ => None,
+    }
+}
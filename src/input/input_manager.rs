@@ -0,0 +1,592 @@
+use std::collections::{HashMap, HashSet};
+
+use log::info;
+use winit::event::{MouseButton, MouseScrollDelta, VirtualKeyCode};
+
+use super::{
+    action::Action,
+    bindings::{Bindings, Modifiers, Trigger},
+    gamepad::{
+        AxisDirection, DeadZones, GamepadAxis, GamepadBackend, GamepadButton, GamepadEvent,
+        GamepadId, GamepadState,
+    },
+    tick_input::TickInput,
+};
+
+/// Roughly how many pixels a touchpad scrolls for each line a mouse wheel
+/// would, so both can be measured in lines.
+const PIXELS_PER_LINE: f32 = 20.0;
+
+/// How far a stick or trigger has to be pushed to count as pressed, and how
+/// far it has to come back to count as released. The gap stops a stick held
+/// near the threshold from flickering between the two.
+const AXIS_PRESS_THRESHOLD: f32 = 0.5;
+const AXIS_RELEASE_THRESHOLD: f32 = 0.4;
+
+/// Keeps track of what's held down, what changed this frame, and where the
+/// mouse is.
+///
+/// Gamepad inputs are merged with the keyboard and mouse, so bindings treat
+/// them all alike. Any number of gamepads can be connected, and they share the
+/// same bindings.
+///
+/// Anything measured per frame, like keys just pressed or how far the mouse
+/// moved, builds up as events arrive until `end_frame` is called. The same
+/// is measured per simulation step, building up until `take_tick_input`.
+pub struct InputManager {
+    bindings: Bindings,
+    held: HashSet<Trigger>,
+    just_pressed: HashSet<Trigger>,
+    just_released: HashSet<Trigger>,
+    modifiers: Modifiers,
+    /// In physical pixels from the top left of the window, or None when the
+    /// cursor is outside it.
+    cursor_position: Option<(f64, f64)>,
+    /// In lines, positive when scrolling up or right.
+    scroll_delta: (f32, f32),
+    /// Raw mouse movement, unaffected by the cursor being grabbed or reaching
+    /// the edge of the screen.
+    mouse_delta: (f64, f64),
+    /// Triggers pressed since the last simulation step, which may be several
+    /// frames ago.
+    tick_presses: HashSet<Trigger>,
+    tick_mouse_delta: (f64, f64),
+    gamepad_backend: Option<Box<dyn GamepadBackend>>,
+    gamepads: HashMap<GamepadId, GamepadState>,
+    dead_zones: DeadZones,
+}
+
+impl InputManager {
+    pub fn new(bindings: Bindings) -> Self {
+        Self {
+            bindings,
+            held: HashSet::new(),
+            just_pressed: HashSet::new(),
+            just_released: HashSet::new(),
+            modifiers: Modifiers::NONE,
+            cursor_position: None,
+            scroll_delta: (0.0, 0.0),
+            mouse_delta: (0.0, 0.0),
+            tick_presses: HashSet::new(),
+            tick_mouse_delta: (0.0, 0.0),
+            gamepad_backend: None,
+            gamepads: HashMap::new(),
+            dead_zones: DeadZones::default(),
+        }
+    }
+
+    /// Reads gamepads from `backend` from now on, replacing any previous
+    /// backend.
+    pub fn set_gamepad_backend(&mut self, backend: Box<dyn GamepadBackend>) {
+        self.gamepad_backend = Some(backend);
+    }
+
+    pub fn bindings(&self) -> &Bindings {
+        &self.bindings
+    }
+
+    /// For rebinding actions while the game is running.
+    pub fn bindings_mut(&mut self) -> &mut Bindings {
+        &mut self.bindings
+    }
+
+    pub fn key_pressed(&self, key: VirtualKeyCode) -> bool {
+        self.held.contains(&Trigger::Key(key))
+    }
+
+    pub fn key_just_pressed(&self, key: VirtualKeyCode) -> bool {
+        self.just_pressed.contains(&Trigger::Key(key))
+    }
+
+    pub fn key_just_released(&self, key: VirtualKeyCode) -> bool {
+        self.just_released.contains(&Trigger::Key(key))
+    }
+
+    pub fn mouse_button_pressed(&self, button: MouseButton) -> bool {
+        self.held.contains(&Trigger::Mouse(button))
+    }
+
+    pub fn mouse_button_just_pressed(&self, button: MouseButton) -> bool {
+        self.just_pressed.contains(&Trigger::Mouse(button))
+    }
+
+    pub fn mouse_button_just_released(&self, button: MouseButton) -> bool {
+        self.just_released.contains(&Trigger::Mouse(button))
+    }
+
+    pub fn set_key_state(&mut self, key: VirtualKeyCode, pressed: bool) {
+        self.set_trigger_state(Trigger::Key(key), pressed);
+    }
+
+    pub fn set_mouse_button_state(&mut self, button: MouseButton, pressed: bool) {
+        self.set_trigger_state(Trigger::Mouse(button), pressed);
+    }
+
+    pub fn modifiers(&self) -> Modifiers {
+        self.modifiers
+    }
+
+    pub fn set_modifiers(&mut self, modifiers: Modifiers) {
+        self.modifiers = modifiers;
+    }
+
+    pub fn cursor_position(&self) -> Option<(f64, f64)> {
+        self.cursor_position
+    }
+
+    pub fn set_cursor_position(&mut self, position: Option<(f64, f64)>) {
+        self.cursor_position = position;
+    }
+
+    /// How far the scroll wheel has moved this frame, in lines.
+    pub fn scroll_delta(&self) -> (f32, f32) {
+        self.scroll_delta
+    }
+
+    pub fn add_scroll(&mut self, delta: MouseScrollDelta) {
+        let (x, y) = match delta {
+            MouseScrollDelta::LineDelta(x, y) => (x, y),
+            MouseScrollDelta::PixelDelta(position) => (
+                position.x as f32 / PIXELS_PER_LINE,
+                position.y as f32 / PIXELS_PER_LINE,
+            ),
+        };
+        self.scroll_delta.0 += x;
+        self.scroll_delta.1 += y;
+    }
+
+    /// How far the mouse has moved this frame, in unscaled device units.
+    pub fn mouse_delta(&self) -> (f64, f64) {
+        self.mouse_delta
+    }
+
+    pub fn add_mouse_motion(&mut self, delta: (f64, f64)) {
+        self.mouse_delta.0 += delta.0;
+        self.mouse_delta.1 += delta.1;
+        self.tick_mouse_delta.0 += delta.0;
+        self.tick_mouse_delta.1 += delta.1;
+    }
+
+    pub fn dead_zones(&self) -> DeadZones {
+        self.dead_zones
+    }
+
+    pub fn set_dead_zones(&mut self, dead_zones: DeadZones) {
+        self.dead_zones = dead_zones;
+        self.update_gamepad_triggers();
+    }
+
+    /// Handles every gamepad event that's arrived since the last call.
+    pub fn poll_gamepads(&mut self) {
+        while let Some(event) = self
+            .gamepad_backend
+            .as_mut()
+            .and_then(|backend| backend.next_event())
+        {
+            self.handle_gamepad_event(event);
+        }
+    }
+
+    pub fn handle_gamepad_event(&mut self, event: GamepadEvent) {
+        match event {
+            GamepadEvent::Connected(id) => {
+                info!("Gamepad {} connected", id.0);
+                self.gamepads.entry(id).or_default();
+            }
+            GamepadEvent::Disconnected(id) => {
+                info!("Gamepad {} disconnected", id.0);
+                // Releases whatever it was holding down.
+                self.gamepads.remove(&id);
+            }
+            // Gamepads that were plugged in before the backend started may
+            // not have been announced.
+            GamepadEvent::Button {
+                id,
+                button,
+                pressed,
+            } => self
+                .gamepads
+                .entry(id)
+                .or_default()
+                .set_button(button, pressed),
+            GamepadEvent::Axis { id, axis, value } => {
+                self.gamepads.entry(id).or_default().set_axis(axis, value)
+            }
+        }
+        self.update_gamepad_triggers();
+    }
+
+    pub fn gamepad_connected(&self, id: GamepadId) -> bool {
+        self.gamepads.contains_key(&id)
+    }
+
+    pub fn gamepad_button_pressed(&self, button: GamepadButton) -> bool {
+        self.held.contains(&Trigger::GamepadButton(button))
+    }
+
+    pub fn gamepad_button_just_pressed(&self, button: GamepadButton) -> bool {
+        self.just_pressed.contains(&Trigger::GamepadButton(button))
+    }
+
+    pub fn gamepad_button_just_released(&self, button: GamepadButton) -> bool {
+        self.just_released.contains(&Trigger::GamepadButton(button))
+    }
+
+    /// An axis's value with dead zones applied. With several gamepads, the
+    /// one pushed furthest wins.
+    pub fn gamepad_axis(&self, axis: GamepadAxis) -> f32 {
+        self.gamepads
+            .values()
+            .map(|gamepad| gamepad.axis(axis, self.dead_zones))
+            .fold(0.0, |strongest, value| {
+                if value.abs() > strongest.abs() {
+                    value
+                } else {
+                    strongest
+                }
+            })
+    }
+
+    /// Whether any of an action's bindings is held down.
+    pub fn action_pressed(&self, action: Action) -> bool {
+        self.action_matches(action, &self.held)
+    }
+
+    /// Whether any of an action's bindings was pressed this frame.
+    pub fn action_just_pressed(&self, action: Action) -> bool {
+        self.action_matches(action, &self.just_pressed)
+    }
+
+    /// Whether any of an action's bindings was let go of this frame.
+    pub fn action_just_released(&self, action: Action) -> bool {
+        self.action_matches(action, &self.just_released)
+    }
+
+    /// How strongly an action is being performed, from 0 to 1. Keys and
+    /// buttons are either 0 or 1, while sticks and triggers give how far
+    /// they're pushed.
+    pub fn action_value(&self, action: Action) -> f32 {
+        self.bindings
+            .bindings_for(action)
+            .iter()
+            .filter(|binding| {
+                self.bindings
+                    .matches(action, binding.trigger, self.modifiers)
+            })
+            .map(|binding| match binding.trigger {
+                Trigger::GamepadAxis(axis, direction) => {
+                    (self.gamepad_axis(axis) * direction.sign()).max(0.0)
+                }
+                trigger if self.held.contains(&trigger) => 1.0,
+                _ => 0.0,
+            })
+            .fold(0.0, f32::max)
+    }
+
+    /// Lets go of every key and mouse button, e.g. when the window loses
+    /// focus and won't hear about them being released. Gamepads are read
+    /// whether or not the window has focus, so are left alone.
+    pub fn release_all(&mut self) {
+        let released: Vec<Trigger> = self
+            .held
+            .iter()
+            .filter(|trigger| !trigger.is_gamepad())
+            .copied()
+            .collect();
+        for trigger in released {
+            self.set_trigger_state(trigger, false);
+        }
+        self.modifiers = Modifiers::NONE;
+    }
+
+    /// The input for a simulation step, starting afresh for the next one.
+    pub fn take_tick_input(&mut self) -> TickInput {
+        let actions = Action::ALL
+            .into_iter()
+            .map(|action| (action, self.action_value(action)))
+            .filter(|&(_, value)| value > 0.0)
+            .collect();
+        let just_pressed = Action::ALL
+            .into_iter()
+            .filter(|&action| self.action_matches(action, &self.tick_presses))
+            .collect();
+        self.tick_presses.clear();
+        TickInput {
+            actions,
+            just_pressed,
+            mouse_delta: std::mem::take(&mut self.tick_mouse_delta),
+        }
+    }
+
+    /// Clears everything measured per frame. Called once everything has had
+    /// a chance to look at this frame's input.
+    pub fn end_frame(&mut self) {
+        self.just_pressed.clear();
+        self.just_released.clear();
+        self.scroll_delta = (0.0, 0.0);
+        self.mouse_delta = (0.0, 0.0);
+    }
+
+    fn set_trigger_state(&mut self, trigger: Trigger, pressed: bool) {
+        // Held keys repeat their pressed events, which aren't new presses.
+        if pressed {
+            if self.held.insert(trigger) {
+                self.just_pressed.insert(trigger);
+                self.tick_presses.insert(trigger);
+            }
+        } else if self.held.remove(&trigger) {
+            self.just_released.insert(trigger);
+        }
+    }
+
+    /// Brings the held gamepad triggers up to date with every connected
+    /// gamepad's state.
+    fn update_gamepad_triggers(&mut self) {
+        for button in GamepadButton::ALL {
+            let pressed = self
+                .gamepads
+                .values()
+                .any(|gamepad| gamepad.button_pressed(button));
+            self.set_trigger_state(Trigger::GamepadButton(button), pressed);
+        }
+        for axis in GamepadAxis::ALL {
+            let value = self.gamepad_axis(axis);
+            for direction in [AxisDirection::Positive, AxisDirection::Negative] {
+                let trigger = Trigger::GamepadAxis(axis, direction);
+                let threshold = if self.held.contains(&trigger) {
+                    AXIS_RELEASE_THRESHOLD
+                } else {
+                    AXIS_PRESS_THRESHOLD
+                };
+                self.set_trigger_state(trigger, value * direction.sign() > threshold);
+            }
+        }
+    }
+
+    fn action_matches(&self, action: Action, triggers: &HashSet<Trigger>) -> bool {
+        self.bindings.bindings_for(action).iter().any(|binding| {
+            triggers.contains(&binding.trigger)
+                && self
+                    .bindings
+                    .matches(action, binding.trigger, self.modifiers)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use winit::dpi::PhysicalPosition;
+
+    use crate::input::{bindings::Binding, gamepad::FakeGamepadBackend};
+
+    use super::*;
+
+    const PAD: GamepadId = GamepadId(0);
+    const FORWARD: Trigger = Trigger::GamepadAxis(GamepadAxis::LeftStickY, AxisDirection::Positive);
+
+    fn input_manager() -> (FakeGamepadBackend, InputManager) {
+        let mut bindings = Bindings::default();
+        for (action, trigger) in [
+            (Action::MoveForward, FORWARD),
+            (
+                Action::MoveUp,
+                Trigger::GamepadAxis(GamepadAxis::RightTrigger, AxisDirection::Positive),
+            ),
+            (
+                Action::SwitchCamera,
+                Trigger::GamepadButton(GamepadButton::North),
+            ),
+            (Action::MoveRight, Trigger::Key(VirtualKeyCode::D)),
+        ] {
+            let binding = Binding {
+                trigger,
+                modifiers: Modifiers::NONE,
+            };
+            bindings.bind(action, binding).unwrap();
+        }
+
+        let backend = FakeGamepadBackend::new();
+        let mut input_manager = InputManager::new(bindings);
+        input_manager.set_gamepad_backend(Box::new(backend.clone()));
+        backend.connect(PAD);
+        input_manager.poll_gamepads();
+        (backend, input_manager)
+    }
+
+    fn assert_near(value: f32, expected: f32) {
+        assert!(
+            (value - expected).abs() < 1e-5,
+            "{} isn't close to {}",
+            value,
+            expected
+        );
+    }
+
+    #[test]
+    fn repeated_key_presses_are_not_new_presses() {
+        let (_, mut input_manager) = input_manager();
+        input_manager.set_key_state(VirtualKeyCode::D, true);
+        assert!(input_manager.action_just_pressed(Action::MoveRight));
+        input_manager.end_frame();
+
+        // The OS repeats the pressed event while the key is held.
+        input_manager.set_key_state(VirtualKeyCode::D, true);
+        assert!(input_manager.key_pressed(VirtualKeyCode::D));
+        assert!(!input_manager.key_just_pressed(VirtualKeyCode::D));
+        assert!(!input_manager.action_just_pressed(Action::MoveRight));
+    }
+
+    #[test]
+    fn presses_and_releases_last_until_the_end_of_the_frame() {
+        let (_, mut input_manager) = input_manager();
+        input_manager.set_key_state(VirtualKeyCode::D, true);
+        input_manager.set_mouse_button_state(MouseButton::Left, true);
+        assert!(input_manager.key_just_pressed(VirtualKeyCode::D));
+        assert!(input_manager.mouse_button_just_pressed(MouseButton::Left));
+        input_manager.end_frame();
+        assert!(!input_manager.key_just_pressed(VirtualKeyCode::D));
+        assert!(!input_manager.mouse_button_just_pressed(MouseButton::Left));
+
+        input_manager.set_key_state(VirtualKeyCode::D, false);
+        assert!(input_manager.key_just_released(VirtualKeyCode::D));
+        assert!(input_manager.action_just_released(Action::MoveRight));
+        input_manager.end_frame();
+        assert!(!input_manager.key_just_released(VirtualKeyCode::D));
+        assert!(!input_manager.action_just_released(Action::MoveRight));
+        assert!(input_manager.mouse_button_pressed(MouseButton::Left));
+    }
+
+    #[test]
+    fn touchpad_scrolling_is_measured_in_lines() {
+        let (_, mut input_manager) = input_manager();
+        input_manager.add_scroll(MouseScrollDelta::LineDelta(0.0, 1.0));
+        input_manager.add_scroll(MouseScrollDelta::PixelDelta(PhysicalPosition::new(
+            -10.0,
+            2.0 * PIXELS_PER_LINE as f64,
+        )));
+        assert_eq!(input_manager.scroll_delta(), (-0.5, 3.0));
+
+        input_manager.end_frame();
+        assert_eq!(input_manager.scroll_delta(), (0.0, 0.0));
+    }
+
+    #[test]
+    fn sticks_and_triggers_inside_their_dead_zones_do_nothing() {
+        let (backend, mut input_manager) = input_manager();
+        backend.set_axis(PAD, GamepadAxis::LeftStickY, 0.1);
+        backend.set_axis(PAD, GamepadAxis::LeftStickX, 0.1);
+        backend.set_axis(PAD, GamepadAxis::RightTrigger, 0.05);
+        input_manager.poll_gamepads();
+
+        // Both stick axes together are still inside the stick's dead zone.
+        assert_eq!(input_manager.gamepad_axis(GamepadAxis::LeftStickY), 0.0);
+        assert_eq!(input_manager.action_value(Action::MoveForward), 0.0);
+        assert_eq!(input_manager.action_value(Action::MoveUp), 0.0);
+    }
+
+    #[test]
+    fn values_are_rescaled_past_the_dead_zone() {
+        let (backend, mut input_manager) = input_manager();
+        let dead_zones = input_manager.dead_zones();
+        backend.set_axis(PAD, GamepadAxis::LeftStickY, 0.5 + dead_zones.stick / 2.0);
+        backend.set_axis(PAD, GamepadAxis::RightTrigger, 1.0);
+        input_manager.poll_gamepads();
+
+        assert_near(input_manager.action_value(Action::MoveForward), 0.5);
+        assert_near(input_manager.action_value(Action::MoveUp), 1.0);
+
+        backend.set_axis(PAD, GamepadAxis::LeftStickY, -1.0);
+        input_manager.poll_gamepads();
+        assert_near(input_manager.gamepad_axis(GamepadAxis::LeftStickY), -1.0);
+        assert_eq!(input_manager.action_value(Action::MoveForward), 0.0);
+    }
+
+    #[test]
+    fn axes_are_released_further_back_than_they_are_pressed() {
+        let (backend, mut input_manager) = input_manager();
+        input_manager.set_dead_zones(DeadZones {
+            stick: 0.0,
+            trigger: 0.0,
+        });
+        let mut push = |value| {
+            input_manager.end_frame();
+            backend.set_axis(PAD, GamepadAxis::LeftStickY, value);
+            input_manager.poll_gamepads();
+            (
+                input_manager.action_pressed(Action::MoveForward),
+                input_manager.action_just_pressed(Action::MoveForward),
+                input_manager.action_just_released(Action::MoveForward),
+            )
+        };
+
+        assert_eq!(push(AXIS_PRESS_THRESHOLD - 0.05), (false, false, false));
+        assert_eq!(push(AXIS_PRESS_THRESHOLD + 0.05), (true, true, false));
+        // Between the thresholds, so it stays pressed.
+        assert_eq!(push(AXIS_PRESS_THRESHOLD - 0.05), (true, false, false));
+        assert_eq!(push(AXIS_RELEASE_THRESHOLD - 0.05), (false, false, true));
+        assert_eq!(push(AXIS_PRESS_THRESHOLD - 0.05), (false, false, false));
+    }
+
+    #[test]
+    fn disconnecting_a_gamepad_releases_what_it_held() {
+        let (backend, mut input_manager) = input_manager();
+        backend.set_button(PAD, GamepadButton::North, true);
+        backend.set_axis(PAD, GamepadAxis::LeftStickY, 1.0);
+        input_manager.poll_gamepads();
+        assert!(input_manager.action_pressed(Action::SwitchCamera));
+        assert!(input_manager.action_pressed(Action::MoveForward));
+        input_manager.end_frame();
+
+        backend.disconnect(PAD);
+        input_manager.poll_gamepads();
+        assert!(!input_manager.gamepad_connected(PAD));
+        assert!(input_manager.action_just_released(Action::SwitchCamera));
+        assert!(input_manager.action_just_released(Action::MoveForward));
+        assert_eq!(input_manager.action_value(Action::MoveForward), 0.0);
+        assert!(input_manager.take_tick_input().actions.is_empty());
+    }
+
+    #[test]
+    fn the_gamepad_pushed_furthest_wins() {
+        let (backend, mut input_manager) = input_manager();
+        let other = GamepadId(1);
+        backend.set_axis(PAD, GamepadAxis::LeftStickY, 1.0);
+        backend.set_axis(other, GamepadAxis::LeftStickY, -0.5);
+        input_manager.poll_gamepads();
+        assert_near(input_manager.gamepad_axis(GamepadAxis::LeftStickY), 1.0);
+
+        // The other gamepad still holds its stick after this one goes.
+        backend.disconnect(PAD);
+        input_manager.poll_gamepads();
+        assert!(input_manager.gamepad_axis(GamepadAxis::LeftStickY) < 0.0);
+        assert!(!input_manager.held.contains(&FORWARD));
+    }
+
+    #[test]
+    fn releasing_everything_leaves_gamepads_alone() {
+        let (backend, mut input_manager) = input_manager();
+        input_manager.set_key_state(VirtualKeyCode::D, true);
+        backend.set_button(PAD, GamepadButton::North, true);
+        input_manager.poll_gamepads();
+
+        input_manager.release_all();
+        assert!(!input_manager.action_pressed(Action::MoveRight));
+        assert!(input_manager.action_pressed(Action::SwitchCamera));
+    }
+
+    #[test]
+    fn presses_let_go_of_between_steps_still_reach_the_next_step() {
+        let (_, mut input_manager) = input_manager();
+        input_manager.take_tick_input();
+
+        // Pressed and released within a frame, before the step runs.
+        input_manager.set_key_state(VirtualKeyCode::D, true);
+        input_manager.set_key_state(VirtualKeyCode::D, false);
+        input_manager.end_frame();
+
+        let tick_input = input_manager.take_tick_input();
+        assert!(tick_input.action_just_pressed(Action::MoveRight));
+        assert_eq!(tick_input.action_value(Action::MoveRight), 0.0);
+        assert!(input_manager.take_tick_input().just_pressed.is_empty());
+    }
+}
@@ -0,0 +1,7 @@
+pub mod action;
+pub mod bindings;
+pub mod gamepad;
+#[cfg(feature = "gamepad")]
+pub mod gilrs_backend;
+pub mod input_manager;
+pub mod tick_input;
@@ -0,0 +1,40 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use serde::{Deserialize, Serialize};
+
+use super::action::Action;
+
+/// The input a single simulation step runs on.
+///
+/// The simulation only ever sees input through these, so a recorded list of
+/// them plays back exactly the same way.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TickInput {
+    /// How strongly each action is being performed, leaving out those that
+    /// aren't.
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub actions: BTreeMap<Action, f32>,
+    /// Actions started since the previous step, even if they've already
+    /// been let go of again.
+    #[serde(skip_serializing_if = "BTreeSet::is_empty")]
+    pub just_pressed: BTreeSet<Action>,
+    /// Raw mouse movement since the previous step, for looking around.
+    #[serde(skip_serializing_if = "is_zero")]
+    pub mouse_delta: (f64, f64),
+}
+
+impl TickInput {
+    /// How strongly an action is being performed, from 0 to 1.
+    pub fn action_value(&self, action: Action) -> f32 {
+        self.actions.get(&action).copied().unwrap_or(0.0)
+    }
+
+    pub fn action_just_pressed(&self, action: Action) -> bool {
+        self.just_pressed.contains(&action)
+    }
+}
+
+fn is_zero(delta: &(f64, f64)) -> bool {
+    *delta == (0.0, 0.0)
+}
@@ -0,0 +1,280 @@
+use std::{
+    collections::HashMap,
+    sync::{
+        mpsc::{self, Receiver, Sender},
+        Arc,
+    },
+    thread::{self, JoinHandle},
+};
+
+use log::info;
+use ultraviolet as uv;
+
+use crate::{
+    meshing::{
+        chunk_mesh::ChunkMesh,
+        mesher::{Mesher, MesherSettings},
+    },
+    world::{
+        chunk::{Chunk, ChunkPos, CHUNK_WIDTH},
+        deferred_writes::BlockWrite,
+        lighting::Lighting,
+        neighbourhood::NeighbourhoodSnapshot,
+    },
+    worldgen::{decorator::Decorator, terrain_generator::TerrainGenerator},
+};
+
+use super::job_queue::{CancelToken, JobQueue};
+
+/// Everything a worker needs to turn a chunk position into a mesh.
+pub struct ChunkBuilder {
+    pub generator: Box<dyn TerrainGenerator>,
+    pub decorator: Option<Decorator>,
+    pub lighting: Lighting,
+    pub mesher: Mesher,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ChunkJobKind {
+    Generate,
+    Mesh,
+}
+
+enum ChunkJob {
+    Generate(ChunkPos),
+    /// Meshes with the settings current when the job was queued.
+    Mesh(NeighbourhoodSnapshot, MesherSettings),
+}
+
+pub enum ChunkJobOutput {
+    Generated {
+        pos: ChunkPos,
+        /// The chunk, lit as though none of its neighbours were loaded.
+        chunk: Chunk,
+        /// Blocks placed by the chunk's features outside it.
+        overflow: Vec<BlockWrite>,
+    },
+    Meshed {
+        pos: ChunkPos,
+        mesh: ChunkMesh,
+    },
+}
+
+impl ChunkJobOutput {
+    pub fn pos(&self) -> ChunkPos {
+        match self {
+            ChunkJobOutput::Generated { pos, .. } | ChunkJobOutput::Meshed { pos, .. } => *pos,
+        }
+    }
+
+    pub fn kind(&self) -> ChunkJobKind {
+        match self {
+            ChunkJobOutput::Generated { .. } => ChunkJobKind::Generate,
+            ChunkJobOutput::Meshed { .. } => ChunkJobKind::Mesh,
+        }
+    }
+}
+
+/// A pool of threads generating and meshing chunks in the background.
+///
+/// Jobs closest to the focus point are done first. Results are collected on
+/// the main thread with `receive`, which is left to insert chunks into the
+/// world and upload meshes to the GPU.
+pub struct ChunkWorkers {
+    queue: Arc<JobQueue<ChunkJob>>,
+    results: Receiver<(ChunkJobOutput, CancelToken)>,
+    threads: Vec<JoinHandle<()>>,
+    in_flight: HashMap<(ChunkPos, ChunkJobKind), CancelToken>,
+    mesher_settings: MesherSettings,
+}
+
+impl ChunkWorkers {
+    pub fn new(builder: ChunkBuilder, thread_count: usize) -> Self {
+        let mesher_settings = builder.mesher.settings;
+        let builder = Arc::new(builder);
+        let queue = Arc::new(JobQueue::new());
+        let (sender, results) = mpsc::channel();
+
+        let threads = (0..thread_count.max(1))
+            .map(|index| {
+                let builder = builder.clone();
+                let queue = queue.clone();
+                let sender = sender.clone();
+                thread::Builder::new()
+                    .name(format!("chunk-worker-{}", index))
+                    .spawn(move || work(&builder, &queue, &sender))
+                    .expect("Failed to spawn chunk worker.")
+            })
+            .collect();
+        info!("Started {} chunk workers", thread_count.max(1));
+
+        Self {
+            queue,
+            results,
+            threads,
+            in_flight: HashMap::new(),
+            mesher_settings,
+        }
+    }
+
+    /// Leaves one core for the main thread.
+    pub fn default_thread_count() -> usize {
+        thread::available_parallelism()
+            .map(|count| count.get().saturating_sub(1))
+            .unwrap_or(1)
+            .max(1)
+    }
+
+    /// Sets the point jobs are prioritised around.
+    pub fn set_focus(&self, focus: uv::Vec3) {
+        self.queue.set_focus(focus);
+    }
+
+    /// Queues generation of a chunk, unless it's already being generated.
+    pub fn generate(&mut self, pos: ChunkPos) {
+        let key = (pos, ChunkJobKind::Generate);
+        if self.in_flight.contains_key(&key) {
+            return;
+        }
+        let token = CancelToken::new();
+        self.queue
+            .push(ChunkJob::Generate(pos), chunk_center(pos), token.clone());
+        self.in_flight.insert(key, token);
+    }
+
+    pub fn mesher_settings(&self) -> MesherSettings {
+        self.mesher_settings
+    }
+
+    /// Changes the settings used by meshing jobs queued from now on.
+    pub fn set_mesher_settings(&mut self, settings: MesherSettings) {
+        self.mesher_settings = settings;
+    }
+
+    /// Queues meshing of a chunk. Any earlier meshing of the same chunk is
+    /// cancelled, since its result would be out of date.
+    pub fn mesh(&mut self, snapshot: NeighbourhoodSnapshot) {
+        let pos = snapshot.pos;
+        let token = CancelToken::new();
+        if let Some(previous) = self
+            .in_flight
+            .insert((pos, ChunkJobKind::Mesh), token.clone())
+        {
+            previous.cancel();
+        }
+        self.queue.push(
+            ChunkJob::Mesh(snapshot, self.mesher_settings),
+            chunk_center(pos),
+            token,
+        );
+    }
+
+    pub fn is_pending(&self, pos: ChunkPos, kind: ChunkJobKind) -> bool {
+        self.in_flight.contains_key(&(pos, kind))
+    }
+
+    /// Cancels every job for a chunk.
+    pub fn cancel(&mut self, pos: ChunkPos) {
+        self.cancel_where(|job_pos, _| job_pos == pos);
+    }
+
+    /// Cancels every job matching a predicate on its chunk and kind, e.g.
+    /// generating chunks that have fallen out of range.
+    pub fn cancel_where(&mut self, mut predicate: impl FnMut(ChunkPos, ChunkJobKind) -> bool) {
+        self.in_flight.retain(|(pos, kind), token| {
+            let cancel = predicate(*pos, *kind);
+            if cancel {
+                token.cancel();
+            }
+            !cancel
+        });
+    }
+
+    /// Number of jobs queued or being worked on.
+    pub fn pending_count(&self) -> usize {
+        self.in_flight.len()
+    }
+
+    pub fn pending_count_of(&self, kind: ChunkJobKind) -> usize {
+        self.in_flight
+            .keys()
+            .filter(|(_, job_kind)| *job_kind == kind)
+            .count()
+    }
+
+    /// Takes every finished job without waiting. Results of cancelled jobs
+    /// are dropped.
+    pub fn receive(&mut self) -> Vec<ChunkJobOutput> {
+        let mut outputs = Vec::new();
+        for (output, token) in self.results.try_iter() {
+            if token.is_cancelled() {
+                continue;
+            }
+            self.in_flight.remove(&(output.pos(), output.kind()));
+            outputs.push(output);
+        }
+        outputs
+    }
+}
+
+impl Drop for ChunkWorkers {
+    fn drop(&mut self) {
+        self.queue.close();
+        for thread in self.threads.drain(..) {
+            let _ = thread.join();
+        }
+    }
+}
+
+fn work(
+    builder: &ChunkBuilder,
+    queue: &JobQueue<ChunkJob>,
+    results: &Sender<(ChunkJobOutput, CancelToken)>,
+) {
+    // Each worker has its own mesher, so jobs can change its settings.
+    let mut mesher = builder.mesher.clone();
+    while let Some((job, token)) = queue.pop() {
+        let output = match job {
+            ChunkJob::Generate(pos) => {
+                let mut chunk = builder.generator.generate_chunk(pos);
+                let overflow = match &builder.decorator {
+                    Some(decorator) => decorator.decorate(&mut chunk, pos),
+                    None => Vec::new(),
+                };
+                builder.lighting.light_chunk(&mut chunk);
+                ChunkJobOutput::Generated {
+                    pos,
+                    chunk,
+                    overflow,
+                }
+            }
+            ChunkJob::Mesh(snapshot, settings) => {
+                mesher.settings = settings;
+                ChunkJobOutput::Meshed {
+                    pos: snapshot.pos,
+                    mesh: mesher.mesh(&snapshot.neighbourhood()),
+                }
+            }
+        };
+
+        if token.is_cancelled() {
+            continue;
+        }
+        if results.send((output, token)).is_err() {
+            // Nobody is listening any more.
+            return;
+        }
+    }
+}
+
+/// Jobs are prioritised by how close the middle of their chunk is. Height is
+/// ignored, since chunks span the whole height of the world.
+fn chunk_center(pos: ChunkPos) -> uv::Vec3 {
+    let origin = pos.origin();
+    let half_width = CHUNK_WIDTH as f32 / 2.0;
+    uv::Vec3::new(
+        origin.x as f32 + half_width,
+        0.0,
+        origin.z as f32 + half_width,
+    )
+}
@@ -0,0 +1,185 @@
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc, Condvar, Mutex,
+};
+
+use ultraviolet as uv;
+
+/// Lets the owner of a job call it off after it's been queued.
+///
+/// Cancelled jobs are dropped if they haven't started. Workers also check
+/// before handing back a result, so a cancelled job never produces one.
+#[derive(Clone, Default)]
+pub struct CancelToken(Arc<AtomicBool>);
+
+impl CancelToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+struct QueuedJob<T> {
+    job: T,
+    position: uv::Vec3,
+    token: CancelToken,
+}
+
+struct QueueState<T> {
+    jobs: Vec<QueuedJob<T>>,
+    focus: uv::Vec3,
+    closed: bool,
+}
+
+/// Jobs waiting for a worker, handed out closest first.
+///
+/// Each job has a position in the world, and workers always take the job
+/// closest to the focus point (usually the camera). The focus can move at any
+/// time, so the closest job is looked for when a worker asks for one rather
+/// than when the job is queued.
+pub struct JobQueue<T> {
+    state: Mutex<QueueState<T>>,
+    available: Condvar,
+}
+
+impl<T> JobQueue<T> {
+    pub fn new() -> Self {
+        Self {
+            state: Mutex::new(QueueState {
+                jobs: Vec::new(),
+                focus: uv::Vec3::zero(),
+                closed: false,
+            }),
+            available: Condvar::new(),
+        }
+    }
+
+    pub fn push(&self, job: T, position: uv::Vec3, token: CancelToken) {
+        let mut state = self.state.lock().unwrap();
+        state.jobs.push(QueuedJob {
+            job,
+            position,
+            token,
+        });
+        self.available.notify_one();
+    }
+
+    pub fn set_focus(&self, focus: uv::Vec3) {
+        self.state.lock().unwrap().focus = focus;
+    }
+
+    /// Waits for the closest job that hasn't been cancelled. Returns None
+    /// once the queue is closed.
+    pub fn pop(&self) -> Option<(T, CancelToken)> {
+        let mut state = self.state.lock().unwrap();
+        loop {
+            if state.closed {
+                return None;
+            }
+
+            state.jobs.retain(|queued| !queued.token.is_cancelled());
+            let focus = state.focus;
+            let closest = state
+                .jobs
+                .iter()
+                .enumerate()
+                .min_by(|(_, a), (_, b)| {
+                    let a = (a.position - focus).mag_sq();
+                    let b = (b.position - focus).mag_sq();
+                    a.total_cmp(&b)
+                })
+                .map(|(index, _)| index);
+
+            match closest {
+                Some(index) => {
+                    let queued = state.jobs.swap_remove(index);
+                    return Some((queued.job, queued.token));
+                }
+                None => state = self.available.wait(state).unwrap(),
+            }
+        }
+    }
+
+    /// Number of jobs waiting, including cancelled ones not yet dropped.
+    pub fn len(&self) -> usize {
+        self.state.lock().unwrap().jobs.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Wakes every waiting worker and stops handing out jobs.
+    pub fn close(&self) {
+        self.state.lock().unwrap().closed = true;
+        self.available.notify_all();
+    }
+}
+
+impl<T> Default for JobQueue<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Pops `count` jobs. There mustn't be fewer left that aren't cancelled,
+    /// since popping an empty queue waits for another job to be pushed.
+    fn pop_all(queue: &JobQueue<&'static str>, count: usize) -> Vec<&'static str> {
+        (0..count).map(|_| queue.pop().unwrap().0).collect()
+    }
+
+    #[test]
+    fn jobs_closest_to_the_focus_come_first() {
+        let queue = JobQueue::new();
+        queue.push("far", uv::Vec3::new(64.0, 0.0, 0.0), CancelToken::new());
+        queue.push("near", uv::Vec3::new(16.0, 0.0, 0.0), CancelToken::new());
+        queue.push("behind", uv::Vec3::new(-32.0, 0.0, 0.0), CancelToken::new());
+        assert_eq!(pop_all(&queue, 3), ["near", "behind", "far"]);
+        assert!(queue.is_empty());
+    }
+
+    #[test]
+    fn moving_the_focus_changes_the_order() {
+        let queue = JobQueue::new();
+        queue.push("far", uv::Vec3::new(64.0, 0.0, 0.0), CancelToken::new());
+        queue.push("near", uv::Vec3::new(16.0, 0.0, 0.0), CancelToken::new());
+        queue.push("behind", uv::Vec3::new(-32.0, 0.0, 0.0), CancelToken::new());
+
+        // Jobs queued before the focus moved are ordered by where it is now.
+        queue.set_focus(uv::Vec3::new(60.0, 0.0, 0.0));
+        assert_eq!(pop_all(&queue, 3), ["far", "near", "behind"]);
+    }
+
+    #[test]
+    fn cancelled_jobs_are_skipped() {
+        let queue = JobQueue::new();
+        let cancelled = CancelToken::new();
+        queue.push("near", uv::Vec3::new(16.0, 0.0, 0.0), cancelled.clone());
+        queue.push("far", uv::Vec3::new(64.0, 0.0, 0.0), CancelToken::new());
+        cancelled.cancel();
+
+        let (job, token) = queue.pop().unwrap();
+        assert_eq!(job, "far");
+        assert!(!token.is_cancelled());
+        assert!(queue.is_empty());
+    }
+
+    #[test]
+    fn closing_stops_handing_out_jobs() {
+        let queue = JobQueue::new();
+        queue.push("near", uv::Vec3::zero(), CancelToken::new());
+        queue.close();
+        assert!(queue.pop().is_none());
+    }
+}
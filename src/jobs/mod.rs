@@ -0,0 +1,2 @@
+pub mod chunk_workers;
+pub mod job_queue;
@@ -1,24 +1,69 @@
-use camera::{camera::Camera, camera_controller::CameraController, camera_uniform::CameraUniform};
-use image::GenericImageView;
-use input::InputManager;
+use block::registry::BlockRegistry;
+use camera::camera_uniform::CameraUniform;
+use chunk_manager::{ChunkManager, StreamingSettings};
+use depth_texture::DepthTexture;
+use game_loop::{FixedTimestep, SystemClock};
+use input::{action::Action, bindings::Bindings, input_manager::InputManager};
+use jobs::chunk_workers::{ChunkBuilder, ChunkWorkers};
+use light::{
+    light::Light,
+    light_uniform::LightUniform,
+    shadow_map::{ShadowMap, ShadowSettings},
+};
+use log::info;
+use meshing::{
+    block_textures::BlockTextures,
+    mesher::{Mesher, MesherSettings},
+};
+use primitives::instance::{Instance, InstanceBuffer};
+use replay::{InputMode, InputSession};
+use sampler::{Sampler, SamplerSettings};
 use shapes::cube::Cube;
-use texture::Texture;
-use wgpu::BindGroupEntry;
-use winit::{event::Event, event_loop::EventLoop, window::WindowBuilder};
+use simulation::Simulation;
+use texture_array::{TextureArray, TextureArrayBuilder};
+use ultraviolet as uv;
+use winit::{
+    event::Event,
+    event_loop::EventLoop,
+    window::{Window, WindowBuilder},
+};
+use world::lighting::Lighting;
+use worldgen::{
+    biome::Biomes, biome_map::BiomeMap, decorator::Decorator, feature::Feature,
+    noise_terrain::NoiseTerrainGenerator, terrain_generator::TerrainBlocks,
+};
 
-mod camera;
+pub mod block;
+pub mod camera;
+mod chunk_manager;
+mod depth_texture;
 mod device;
 mod event;
-mod input;
+pub mod game_loop;
+pub mod input;
+pub mod jobs;
+mod light;
+pub mod meshing;
 mod pipeline;
-mod primitives;
+pub mod primitives;
+pub mod replay;
+pub mod sampler;
 mod shader;
 mod shapes;
+pub mod simulation;
 mod surface;
-mod texture;
+pub mod texture_array;
 mod window;
+pub mod world;
+pub mod worldgen;
 
-pub async fn run(window_title: &str, window_size: [u32; 2]) {
+const WORLD_SEED: u64 = 0x4c79_7574;
+
+/// Simulation steps per second. Rendering runs as fast as it can, drawing
+/// the simulation part way between its last two steps.
+const TICKS_PER_SECOND: u32 = 60;
+
+pub async fn run(window_title: &str, window_size: [u32; 2], input_mode: InputMode) {
     let instance = wgpu::Instance::default();
 
     let event_loop = EventLoop::new();
@@ -29,7 +74,16 @@ pub async fn run(window_title: &str, window_size: [u32; 2]) {
         .build(&event_loop)
         .expect("Failed to create window.");
 
-    let mut input_manager = InputManager::new();
+    let bindings = Bindings::load("src/assets/bindings.ron")
+        .unwrap_or_else(|error| panic!("Failed to load key bindings: {}", error));
+    let mut input_manager = InputManager::new(bindings);
+    let mut input_session = InputSession::start(input_mode, WORLD_SEED, TICKS_PER_SECOND)
+        .unwrap_or_else(|error| panic!("Failed to start input: {}", error));
+    #[cfg(feature = "gamepad")]
+    match input::gilrs_backend::GilrsBackend::new() {
+        Ok(backend) => input_manager.set_gamepad_backend(Box::new(backend)),
+        Err(error) => log::warn!("Gamepads are unavailable: {}", error),
+    }
 
     // Creates a surface, which is a handle to something we can render images to.
     let surface = surface::create_surface(&instance, &window);
@@ -40,65 +94,87 @@ pub async fn run(window_title: &str, window_size: [u32; 2]) {
     // and a queue.
     let (device, queue) = device::create_device_and_queue(&adapter).await;
 
-    let mut camera = Camera::new(
-        (0.0, 2.0, 3.0).into(),
-        (0.0, 0.0, 0.0).into(),
-        logical_window_size.width as f32 / logical_window_size.height as f32,
+    let mut simulation =
+        Simulation::new(logical_window_size.width as f32 / logical_window_size.height as f32);
+    let mut camera_uniform = CameraUniform::new(simulation.camera(), &device);
+    let mut cursor_grabbed = false;
+    let mut game_loop = FixedTimestep::new(SystemClock::new(), input_session.ticks_per_second());
+    let sun = Light::sun();
+    let light_uniform = LightUniform::new(&sun, &device);
+    let shadow_map = ShadowMap::new(&device, ShadowSettings::default());
+
+    let block_registry = BlockRegistry::load("src/assets/blocks.ron", "src/assets/textures")
+        .unwrap_or_else(|error| panic!("Failed to load blocks: {}", error));
+    let block_textures = TextureArrayBuilder::from_registry(&block_registry, "src/assets/textures")
+        .unwrap_or_else(|error| panic!("Failed to load block textures: {}", error));
+    let block_normal_maps =
+        TextureArrayBuilder::normal_maps_for(&block_textures, "src/assets/textures")
+            .and_then(|builder| builder.build(&device, &queue))
+            .unwrap_or_else(|error| panic!("Failed to build block normal maps: {}", error));
+    let block_texture_array = block_textures
+        .build(&device, &queue)
+        .unwrap_or_else(|error| panic!("Failed to build block textures: {}", error));
+    let sampler = Sampler::new(&device, SamplerSettings::default());
+    let texture_bind_group_layout = TextureArray::get_bind_group_layout(&device);
+    let texture_bind_group = block_texture_array.create_bind_group(
+        &device,
+        &texture_bind_group_layout,
+        &sampler,
+        &block_normal_maps,
     );
-    let mut camera_uniform = CameraUniform::new(&camera, &device);
-    let camera_controller = CameraController::new(0.2);
-
-    let texture = Texture::new(&device, "src/assets/container.jpg");
-    queue.write_texture(
-        texture.get_image_copy(),
-        &texture.image.to_rgba8(),
-        texture.get_image_data_layout(),
-        texture.size,
+
+    let lighting = Lighting::from_registry(&block_registry);
+    let mut chunk_manager = ChunkManager::new(
+        create_chunk_workers(
+            input_session.seed(),
+            &block_registry,
+            &block_texture_array,
+            &lighting,
+        ),
+        lighting,
+        StreamingSettings::default(),
     );
-    let texture_view = texture
-        .inner_texture
-        .create_view(&wgpu::TextureViewDescriptor::default());
-    let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
-        address_mode_u: wgpu::AddressMode::MirrorRepeat,
-        address_mode_v: wgpu::AddressMode::MirrorRepeat,
-        address_mode_w: wgpu::AddressMode::MirrorRepeat,
-        mag_filter: wgpu::FilterMode::Linear,
-        min_filter: wgpu::FilterMode::Nearest,
-        mipmap_filter: wgpu::FilterMode::Nearest,
-        ..Default::default()
-    });
-    let texture_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
-        label: Some("Texture Bind Group"),
-        layout: &texture.get_bind_group_layout(&device),
-        entries: &[
-            BindGroupEntry {
-                binding: 0,
-                resource: wgpu::BindingResource::TextureView(&texture_view),
-            },
-            BindGroupEntry {
-                binding: 1,
-                resource: wgpu::BindingResource::Sampler(&sampler),
-            },
-        ],
-    });
+    let mut chunk_stats = chunk_manager.stats();
+    let window_title = window_title.to_string();
 
     // A handle to a compiled shader module.
-    let shader = shader::create_shader("src/shaders/shader.wgsl", &device);
-    let pipeline_layout =
-        pipeline::create_pipeline_layout(&device, &camera_uniform, Some(&texture));
+    let shader = shader::create_block_shader("src/shaders/shader.wgsl", &device);
+    let pipeline_layout = pipeline::create_pipeline_layout(
+        &device,
+        &camera_uniform,
+        &texture_bind_group_layout,
+        &light_uniform,
+        &shadow_map,
+    );
 
     let swapchain_capabilities = surface.get_capabilities(&adapter);
     let swapchain_format = swapchain_capabilities.formats[0];
 
     let active_render_pipeline =
         pipeline::create_render_pipeline(&device, &pipeline_layout, &shader, swapchain_format);
+    let instanced_shader = shader::create_block_shader("src/shaders/instanced.wgsl", &device);
+    let instanced_render_pipeline = pipeline::create_instanced_render_pipeline(
+        &device,
+        &pipeline_layout,
+        &instanced_shader,
+        swapchain_format,
+    );
+    let shadow_shader = shader::create_shader("src/shaders/shadow.wgsl", &device);
+    let shadow_pipeline = pipeline::create_shadow_pipeline(
+        &device,
+        &pipeline::create_shadow_pipeline_layout(&device, &shadow_map),
+        &shadow_shader,
+    );
     let mut config =
         surface::create_surface_config(swapchain_format, &window, swapchain_capabilities);
 
     surface.configure(&device, &config);
+    let mut depth_texture = DepthTexture::new(&device, &config);
 
     // let triangle = Triangle::new(&device);
     let cube = Cube::new(&device);
+    let cube_instances =
+        InstanceBuffer::new(&device, &create_marker_instances(&block_texture_array));
 
     event_loop.run(move |event, _, control_flow| {
         control_flow.set_poll();
@@ -107,27 +183,64 @@ pub async fn run(window_title: &str, window_size: [u32; 2]) {
             Event::WindowEvent { window_id, event } if window_id == window.id() => {
                 event::handle_window_event(
                     event,
-                    &window,
                     &device,
                     control_flow,
                     &surface,
                     &mut config,
+                    &mut depth_texture,
                     &mut input_manager,
                 )
             }
+            Event::DeviceEvent { event, .. } => {
+                event::handle_device_event(event, &mut input_manager)
+            }
             Event::MainEventsCleared => {
-                // At this point, all input events have been processed.
-                camera_controller.update_camera(&mut camera, &input_manager);
-                camera_uniform.update_view_projection(&mut camera);
+                // At this point, all window input events have been processed.
+                input_manager.poll_gamepads();
+                let frame = game_loop.advance();
+
+                if input_manager.action_just_pressed(Action::ToggleFullscreen) {
+                    window::toggle_fullscreen(&window);
+                }
+                update_cursor_grab(&window, &mut cursor_grabbed, &input_manager);
+                for _ in 0..frame.ticks {
+                    let mut input = input_manager.take_tick_input();
+                    // Without the cursor grabbed, the mouse is free to leave
+                    // the window rather than look around.
+                    if !cursor_grabbed {
+                        input.mouse_delta = (0.0, 0.0);
+                    }
+                    let input = input_session.next_tick(input);
+                    simulation.step(&input, game_loop.step_seconds());
+                }
+                let render_camera = simulation.interpolated_camera(frame.alpha);
+
+                update_view_distance(&mut chunk_manager, &input_manager);
+                update_mesher_settings(&mut chunk_manager, &input_manager);
+                chunk_manager.update(&device, render_camera.eye);
+                if chunk_manager.stats() != chunk_stats {
+                    chunk_stats = chunk_manager.stats();
+                    window.set_title(&format!(
+                        "{} - {} chunks loaded, {} meshed, {} generating, {} meshing, {} triangles",
+                        window_title,
+                        chunk_stats.loaded,
+                        chunk_stats.meshed,
+                        chunk_stats.generating,
+                        chunk_stats.meshing,
+                        chunk_stats.triangles
+                    ));
+                }
+                camera_uniform.update_view_projection(&render_camera);
                 queue.write_buffer(
                     &camera_uniform.buffer,
                     0,
-                    bytemuck::cast_slice(&[camera_uniform.view_projection]),
+                    bytemuck::cast_slice(&[camera_uniform.to_raw()]),
                 );
+                shadow_map.update(&queue, &render_camera, sun.direction);
 
-                input_manager.set_key_state(winit::event::VirtualKeyCode::Space, false);
                 // Time to render the scene.
                 window.request_redraw();
+                input_manager.end_frame();
             }
             Event::RedrawRequested(_) => {
                 // Get the next frame in the swap chain to draw to
@@ -140,6 +253,19 @@ pub async fn run(window_title: &str, window_size: [u32; 2]) {
                 let mut encoder =
                     device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
 
+                // Draw the world from the sun into each shadow cascade first,
+                // so the main pass can tell what's in shadow.
+                for cascade in 0..shadow_map.cascade_count() {
+                    let mut shadow_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                        label: Some("Shadow Pass"),
+                        color_attachments: &[],
+                        depth_stencil_attachment: Some(shadow_map.get_attachment(cascade)),
+                    });
+                    shadow_pass.set_pipeline(&shadow_pipeline);
+                    shadow_pass.set_bind_group(0, &shadow_map.cascade_bind_groups[cascade], &[]);
+                    chunk_manager.draw(&mut shadow_pass);
+                }
+
                 // Create a render pass, which is a type of command buffer
                 let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
                     label: Some("Render Pass"), // for debugging
@@ -156,16 +282,22 @@ pub async fn run(window_title: &str, window_size: [u32; 2]) {
                             store: true,
                         },
                     })],
-                    depth_stencil_attachment: None,
+                    depth_stencil_attachment: Some(depth_texture.get_attachment()),
                 });
                 render_pass.set_pipeline(&active_render_pipeline);
                 render_pass.set_bind_group(0, &texture_bind_group, &[]);
                 render_pass.set_bind_group(1, &camera_uniform.bind_group, &[]);
+                render_pass.set_bind_group(2, &light_uniform.bind_group, &[]);
+                render_pass.set_bind_group(3, &shadow_map.bind_group, &[]);
+                chunk_manager.draw(&mut render_pass);
+
+                // Every marker cube is drawn with a single call.
+                render_pass.set_pipeline(&instanced_render_pipeline);
                 render_pass.set_vertex_buffer(0, cube.vertex_buffer.slice(..));
+                render_pass.set_vertex_buffer(1, cube_instances.buffer.slice(..));
                 render_pass
                     .set_index_buffer(cube.index_buffer.slice(..), wgpu::IndexFormat::Uint16);
-                // Draw something with 3 vertices and 1 instance.
-                render_pass.draw_indexed(0..Cube::get_indices_len(), 0, 0..1);
+                render_pass.draw_indexed(0..Cube::get_indices_len(), 0, 0..cube_instances.count);
 
                 // We drop render_pass so we can call encoder.finish(),
                 // since render_pass borrows encoder mutably.
@@ -174,7 +306,114 @@ pub async fn run(window_title: &str, window_size: [u32; 2]) {
                 queue.submit(Some(encoder.finish()));
                 frame.present();
             }
+            Event::LoopDestroyed => {
+                if let Err(error) = input_session.finish() {
+                    log::error!("Failed to save recording: {}", error);
+                }
+            }
             _ => (),
         }
     });
 }
+
+/// A grid of small tinted cubes floating above the world origin, marking
+/// where the camera starts out looking.
+fn create_marker_instances(textures: &TextureArray) -> Vec<Instance> {
+    const GRID_SIZE: i32 = 8;
+
+    let mut instances = Vec::new();
+    for z in 0..GRID_SIZE {
+        for x in 0..GRID_SIZE {
+            let mut instance = Instance::new(uv::Vec3::new(
+                (x - GRID_SIZE / 2) as f32 * 2.0,
+                120.0,
+                (z - GRID_SIZE / 2) as f32 * 2.0,
+            ));
+            instance.rotation = uv::Rotor3::from_rotation_xz((x + z) as f32 * 0.2);
+            // Cubes are two units across, so this makes them one block wide.
+            instance.scale = 0.5;
+            instance.texture_layer = textures.layer("glowstone").unwrap_or_default();
+            instance.tint = uv::Vec4::new(
+                x as f32 / GRID_SIZE as f32,
+                z as f32 / GRID_SIZE as f32,
+                1.0,
+                1.0,
+            );
+            instances.push(instance);
+        }
+    }
+    instances
+}
+
+/// Grabs the cursor for looking around with the mouse, or lets it go.
+fn update_cursor_grab(window: &Window, cursor_grabbed: &mut bool, input_manager: &InputManager) {
+    if input_manager.action_just_pressed(Action::ToggleCursorGrab) {
+        *cursor_grabbed = !*cursor_grabbed;
+        window::set_cursor_grabbed(window, *cursor_grabbed);
+    }
+}
+
+/// Changes the view distance by one chunk at a time.
+fn update_view_distance(chunk_manager: &mut ChunkManager, input_manager: &InputManager) {
+    let view_distance = chunk_manager.settings().view_distance;
+    if input_manager.action_just_pressed(Action::IncreaseViewDistance) {
+        chunk_manager.set_view_distance(view_distance + 1);
+    }
+    if input_manager.action_just_pressed(Action::DecreaseViewDistance) {
+        chunk_manager.set_view_distance(view_distance - 1);
+    }
+    if chunk_manager.settings().view_distance != view_distance {
+        info!(
+            "View distance set to {} chunks",
+            chunk_manager.settings().view_distance
+        );
+    }
+}
+
+/// Turns ambient occlusion on and off, and switches between meshing modes,
+/// so the way chunks look and how many triangles they take can be compared.
+fn update_mesher_settings(chunk_manager: &mut ChunkManager, input_manager: &InputManager) {
+    let mut settings = chunk_manager.mesher_settings();
+    if input_manager.action_just_pressed(Action::ToggleAmbientOcclusion) {
+        settings.ambient_occlusion = !settings.ambient_occlusion;
+    }
+    if input_manager.action_just_pressed(Action::ToggleMeshingMode) {
+        settings.mode = settings.mode.toggled();
+    }
+    if settings != chunk_manager.mesher_settings() {
+        info!("Meshing chunks again with {:?}", settings);
+        chunk_manager.set_mesher_settings(settings);
+    }
+}
+
+fn create_chunk_workers(
+    seed: u64,
+    registry: &BlockRegistry,
+    textures: &TextureArray,
+    lighting: &Lighting,
+) -> ChunkWorkers {
+    let blocks = TerrainBlocks::from_registry(registry)
+        .unwrap_or_else(|error| panic!("Missing terrain blocks: {}", error));
+    let biomes = Biomes::from_registry(registry)
+        .unwrap_or_else(|error| panic!("Missing biome blocks: {}", error));
+    let features = Feature::load_all("src/assets/features.ron", registry, &biomes)
+        .unwrap_or_else(|error| panic!("Failed to load features: {}", error));
+
+    let decorator = Decorator::new(seed, features, &biomes);
+    let generator = NoiseTerrainGenerator::new(seed, blocks, BiomeMap::new(seed, biomes));
+    let mesher = Mesher::new(
+        registry,
+        BlockTextures::new(registry, |name| textures.layer(name)),
+        MesherSettings::default(),
+    );
+
+    ChunkWorkers::new(
+        ChunkBuilder {
+            generator: Box::new(generator),
+            decorator: Some(decorator),
+            lighting: lighting.clone(),
+            mesher,
+        },
+        ChunkWorkers::default_thread_count(),
+    )
+}
@@ -0,0 +1,26 @@
+use ultraviolet as uv;
+
+/// A directional light, such as the sun, lighting everything from the same
+/// direction.
+pub struct Light {
+    /// The direction the light travels in.
+    pub direction: uv::Vec3,
+    pub colour: uv::Vec3,
+    /// Light reaching surfaces facing away from the light.
+    pub ambient: f32,
+    /// How bright and how tight specular highlights are.
+    pub specular_strength: f32,
+    pub shininess: f32,
+}
+
+impl Light {
+    pub fn sun() -> Self {
+        Self {
+            direction: uv::Vec3::new(-0.4, -1.0, -0.3).normalized(),
+            colour: uv::Vec3::new(1.0, 0.97, 0.9),
+            ambient: 0.35,
+            specular_strength: 0.2,
+            shininess: 32.0,
+        }
+    }
+}
@@ -0,0 +1,73 @@
+use bytemuck_derive::{Pod, Zeroable};
+use wgpu::util::DeviceExt;
+
+use super::light::Light;
+
+/// The light as laid out in the uniform buffer. vec3s are aligned to 16
+/// bytes, so each is followed by a scalar to fill the gap.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Pod, Zeroable)]
+pub struct LightUniformRaw {
+    pub direction: [f32; 3],
+    pub specular_strength: f32,
+    pub colour: [f32; 3],
+    pub ambient: f32,
+    pub shininess: f32,
+    _padding: [f32; 3],
+}
+
+impl LightUniformRaw {
+    pub fn new(light: &Light) -> Self {
+        Self {
+            direction: light.direction.normalized().into(),
+            specular_strength: light.specular_strength,
+            colour: light.colour.into(),
+            ambient: light.ambient,
+            shininess: light.shininess,
+            _padding: [0.0; 3],
+        }
+    }
+}
+
+pub struct LightUniform {
+    pub bind_group: wgpu::BindGroup,
+    pub layout: wgpu::BindGroupLayout,
+}
+
+impl LightUniform {
+    pub fn new(light: &Light, device: &wgpu::Device) -> Self {
+        let buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Light Buffer"),
+            contents: bytemuck::cast_slice(&[LightUniformRaw::new(light)]),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+
+        let layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Light Bind Group Layout"),
+            entries: &[wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            }],
+        });
+
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Light Bind Group"),
+            layout: &layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: buffer.as_entire_binding(),
+            }],
+        });
+
+        LightUniform {
+            layout,
+            bind_group,
+        }
+    }
+}
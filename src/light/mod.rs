@@ -0,0 +1,4 @@
+#[allow(clippy::module_inception)]
+pub mod light;
+pub mod light_uniform;
+pub mod shadow_map;
@@ -0,0 +1,347 @@
+use bytemuck_derive::{Pod, Zeroable};
+use ultraviolet as uv;
+use wgpu::util::DeviceExt;
+
+use crate::{camera::camera::Camera, depth_texture::DEPTH_FORMAT};
+
+/// Most cascades the shaders can sample from.
+pub const MAX_CASCADES: usize = 4;
+
+/// How far behind a cascade shadow casters are still drawn, so that tall
+/// things outside the view still cast shadows into it.
+const CASTER_MARGIN: f32 = 256.0;
+
+#[derive(Debug, Clone, Copy)]
+pub struct ShadowSettings {
+    /// Number of cascades, from 1 to `MAX_CASCADES`. Each covers a slice of
+    /// the view further from the camera than the last.
+    pub cascade_count: usize,
+    /// Width and height of each cascade's shadow map, in texels.
+    pub resolution: u32,
+    /// Distance from the camera past which nothing is shadowed.
+    pub max_distance: f32,
+    /// Blends between splitting cascades evenly (0) and logarithmically (1).
+    /// Logarithmic splits give nearby shadows more detail.
+    pub split_lambda: f32,
+    /// Texels sampled either side of each fragment for percentage-closer
+    /// filtering. 0 gives hard edges.
+    pub pcf_radius: u32,
+}
+
+impl Default for ShadowSettings {
+    fn default() -> Self {
+        Self {
+            cascade_count: 3,
+            resolution: 2048,
+            max_distance: 160.0,
+            split_lambda: 0.75,
+            pcf_radius: 1,
+        }
+    }
+}
+
+/// Shadow data read by the main shaders, as laid out in the uniform buffer.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Pod, Zeroable)]
+struct ShadowUniformRaw {
+    light_view_projections: [uv::Mat4; MAX_CASCADES],
+    /// View depth at which each cascade ends.
+    split_depths: [f32; MAX_CASCADES],
+    /// Width of a texel in each cascade, in world units.
+    texel_world_sizes: [f32; MAX_CASCADES],
+    cascade_count: u32,
+    pcf_radius: u32,
+    texel_size: f32,
+    _padding: f32,
+}
+
+/// Depth maps of the world as seen by a directional light, one per cascade,
+/// for working out which fragments are in shadow.
+pub struct ShadowMap {
+    settings: ShadowSettings,
+    cascade_views: Vec<wgpu::TextureView>,
+    uniform_buffer: wgpu::Buffer,
+    cascade_buffers: Vec<wgpu::Buffer>,
+    /// Bound while rendering each cascade, holding its light view projection.
+    pub cascade_bind_groups: Vec<wgpu::BindGroup>,
+    pub cascade_layout: wgpu::BindGroupLayout,
+    /// Bound while rendering the scene, for sampling the shadow maps.
+    pub bind_group: wgpu::BindGroup,
+    pub layout: wgpu::BindGroupLayout,
+}
+
+impl ShadowMap {
+    pub fn new(device: &wgpu::Device, settings: ShadowSettings) -> Self {
+        let settings = ShadowSettings {
+            cascade_count: settings.cascade_count.clamp(1, MAX_CASCADES),
+            resolution: settings
+                .resolution
+                .clamp(1, device.limits().max_texture_dimension_2d),
+            ..settings
+        };
+
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Shadow Map Texture"),
+            size: wgpu::Extent3d {
+                width: settings.resolution,
+                height: settings.resolution,
+                depth_or_array_layers: settings.cascade_count as u32,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: DEPTH_FORMAT,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
+            view_formats: &[],
+        });
+        let array_view = texture.create_view(&wgpu::TextureViewDescriptor {
+            label: Some("Shadow Map View"),
+            dimension: Some(wgpu::TextureViewDimension::D2Array),
+            ..Default::default()
+        });
+        let cascade_views = (0..settings.cascade_count as u32)
+            .map(|cascade| {
+                texture.create_view(&wgpu::TextureViewDescriptor {
+                    label: Some("Shadow Cascade View"),
+                    dimension: Some(wgpu::TextureViewDimension::D2),
+                    base_array_layer: cascade,
+                    array_layer_count: Some(1),
+                    ..Default::default()
+                })
+            })
+            .collect();
+
+        // Comparing against the stored depth with linear filtering blends
+        // the results of the four nearest texels, softening edges for free.
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("Shadow Map Sampler"),
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            address_mode_w: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            mipmap_filter: wgpu::FilterMode::Nearest,
+            compare: Some(wgpu::CompareFunction::LessEqual),
+            ..Default::default()
+        });
+
+        let uniform_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Shadow Buffer"),
+            size: std::mem::size_of::<ShadowUniformRaw>() as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Shadow Bind Group Layout"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Depth,
+                        view_dimension: wgpu::TextureViewDimension::D2Array,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Comparison),
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+            ],
+        });
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Shadow Bind Group"),
+            layout: &layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&array_view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(&sampler),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: uniform_buffer.as_entire_binding(),
+                },
+            ],
+        });
+
+        let cascade_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Shadow Cascade Bind Group Layout"),
+            entries: &[wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::VERTEX,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            }],
+        });
+        let cascade_buffers: Vec<wgpu::Buffer> = (0..settings.cascade_count)
+            .map(|_| {
+                device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                    label: Some("Shadow Cascade Buffer"),
+                    contents: bytemuck::cast_slice(&[uv::Mat4::identity()]),
+                    usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+                })
+            })
+            .collect();
+        let cascade_bind_groups = cascade_buffers
+            .iter()
+            .map(|buffer| {
+                device.create_bind_group(&wgpu::BindGroupDescriptor {
+                    label: Some("Shadow Cascade Bind Group"),
+                    layout: &cascade_layout,
+                    entries: &[wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: buffer.as_entire_binding(),
+                    }],
+                })
+            })
+            .collect();
+
+        Self {
+            settings,
+            cascade_views,
+            uniform_buffer,
+            cascade_buffers,
+            cascade_bind_groups,
+            cascade_layout,
+            bind_group,
+            layout,
+        }
+    }
+
+    pub fn cascade_count(&self) -> usize {
+        self.settings.cascade_count
+    }
+
+    /// Fits each cascade around its slice of the camera's view, looking
+    /// along the light's direction.
+    pub fn update(&self, queue: &wgpu::Queue, camera: &Camera, light_direction: uv::Vec3) {
+        let split_depths = self.split_depths(camera.z_near());
+
+        let mut raw = ShadowUniformRaw {
+            light_view_projections: [uv::Mat4::identity(); MAX_CASCADES],
+            split_depths: [0.0; MAX_CASCADES],
+            texel_world_sizes: [0.0; MAX_CASCADES],
+            cascade_count: self.settings.cascade_count as u32,
+            pcf_radius: self.settings.pcf_radius,
+            texel_size: 1.0 / self.settings.resolution as f32,
+            _padding: 0.0,
+        };
+        let mut near = camera.z_near();
+        for (cascade, &far) in split_depths.iter().enumerate() {
+            let (view_projection, radius) = self.fit_cascade(camera, near, far, light_direction);
+            queue.write_buffer(
+                &self.cascade_buffers[cascade],
+                0,
+                bytemuck::cast_slice(&[view_projection]),
+            );
+            raw.light_view_projections[cascade] = view_projection;
+            raw.split_depths[cascade] = far;
+            raw.texel_world_sizes[cascade] = radius * 2.0 / self.settings.resolution as f32;
+            near = far;
+        }
+
+        queue.write_buffer(&self.uniform_buffer, 0, bytemuck::cast_slice(&[raw]));
+    }
+
+    /// Clears a cascade's depth before rendering into it.
+    pub fn get_attachment(&self, cascade: usize) -> wgpu::RenderPassDepthStencilAttachment<'_> {
+        wgpu::RenderPassDepthStencilAttachment {
+            view: &self.cascade_views[cascade],
+            depth_ops: Some(wgpu::Operations {
+                load: wgpu::LoadOp::Clear(1.0),
+                store: true,
+            }),
+            stencil_ops: None,
+        }
+    }
+
+    /// View depth at which each cascade ends, blending even and logarithmic
+    /// splits of the distance up to `max_distance`.
+    fn split_depths(&self, z_near: f32) -> Vec<f32> {
+        let count = self.settings.cascade_count;
+        let far = self.settings.max_distance.max(z_near);
+        (1..=count)
+            .map(|i| {
+                let fraction = i as f32 / count as f32;
+                let logarithmic = z_near * (far / z_near).powf(fraction);
+                let even = z_near + (far - z_near) * fraction;
+                even + (logarithmic - even) * self.settings.split_lambda
+            })
+            .collect()
+    }
+
+    /// An orthographic view projection from the light, covering a sphere
+    /// around the slice of the frustum between `near` and `far`, along with
+    /// the sphere's radius.
+    ///
+    /// Fitting a sphere rather than a box keeps the cascade the same size as
+    /// the camera turns, and snapping it to whole texels stops shadow edges
+    /// from shimmering as the camera moves.
+    fn fit_cascade(
+        &self,
+        camera: &Camera,
+        near: f32,
+        far: f32,
+        direction: uv::Vec3,
+    ) -> (uv::Mat4, f32) {
+        let corners = camera.frustum_corners(near, far);
+        let mut center = uv::Vec3::zero();
+        for corner in corners {
+            center += corner;
+        }
+        center /= corners.len() as f32;
+        let mut radius = 0.0f32;
+        for corner in corners {
+            radius = radius.max((corner - center).mag());
+        }
+        radius = (radius * 16.0).ceil() / 16.0;
+
+        let direction = direction.normalized();
+        // look_at can't work out which way is up when looking straight up
+        // or down.
+        let up = if direction.y.abs() > 0.99 {
+            uv::Vec3::unit_z()
+        } else {
+            uv::Vec3::unit_y()
+        };
+        let rotation = uv::Mat4::look_at(uv::Vec3::zero(), direction, up);
+        let texel_size = radius * 2.0 / self.settings.resolution as f32;
+        let mut light_center = rotation.transform_point3(center);
+        light_center.x = (light_center.x / texel_size).floor() * texel_size;
+        light_center.y = (light_center.y / texel_size).floor() * texel_size;
+        let center = rotation.inversed().transform_point3(light_center);
+
+        let eye = center - direction * (radius + CASTER_MARGIN);
+        let view = uv::Mat4::look_at(eye, center, up);
+        let projection = uv::projection::orthographic_wgpu_dx(
+            -radius,
+            radius,
+            -radius,
+            radius,
+            0.0,
+            radius * 2.0 + CASTER_MARGIN,
+        );
+        (projection * view, radius)
+    }
+}
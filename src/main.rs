@@ -1,12 +1,47 @@
 use log::info;
-use lyutmc::run;
+use lyutmc::{
+    replay::{InputMode, Recording},
+    run,
+};
+
+const WINDOW_SIZE: [u32; 2] = [1920, 1080];
 
 fn main() {
     env_logger::init();
     info!("Starting LyutMC");
 
-    const WINDOW_SIZE: [u32; 2] = [1920, 1080];
     let window_title = "LyutMC";
 
-    pollster::block_on(run(window_title, WINDOW_SIZE));
+    let mut args = std::env::args().skip(1);
+    let input_mode = match (args.next().as_deref(), args.next()) {
+        (Some("--record"), Some(path)) => InputMode::Record(path),
+        (Some("--replay"), Some(path)) => InputMode::Replay(path),
+        (Some("--replay-headless"), Some(path)) => return replay_headless(&path),
+        (None, _) => InputMode::Live,
+        _ => {
+            eprintln!(
+                "Usage: lyutmc [--record <file> | --replay <file> | --replay-headless <file>]"
+            );
+            std::process::exit(2);
+        }
+    };
+
+    pollster::block_on(run(window_title, WINDOW_SIZE, input_mode));
+}
+
+/// Plays a recording through without opening a window, and prints where the
+/// player ends up. No world is generated, since nothing the simulation does
+/// depends on it.
+fn replay_headless(path: &str) {
+    let recording =
+        Recording::load(path).unwrap_or_else(|error| panic!("Failed to load recording: {}", error));
+    let simulation = recording.replay(WINDOW_SIZE[0] as f32 / WINDOW_SIZE[1] as f32);
+    let camera = simulation.camera();
+    println!(
+        "Replayed {} ticks with seed {:#x}: eye {:?}, target {:?}",
+        simulation.tick(),
+        recording.seed,
+        camera.eye,
+        camera.target
+    );
 }
@@ -0,0 +1,54 @@
+use crate::{
+    block::{face::Face, registry::BlockRegistry},
+    world::chunk::BlockId,
+};
+
+/// The texture layer drawn on each face of every block.
+#[derive(Clone)]
+pub struct BlockTextures {
+    // Indexed by block ID, then by face. Blocks without textures are None.
+    layers: Vec<Option<[u32; 6]>>,
+}
+
+impl BlockTextures {
+    /// Resolves each block's texture names with `layer_of`.
+    ///
+    /// Faces whose texture can't be resolved use layer 0.
+    pub fn new(registry: &BlockRegistry, layer_of: impl Fn(&str) -> Option<u32>) -> Self {
+        let mut layers = Vec::new();
+        for block in registry.blocks() {
+            let index = block.id as usize;
+            if index >= layers.len() {
+                layers.resize(index + 1, None);
+            }
+            if block.textures.is_some() {
+                layers[index] = Some(
+                    Face::ALL
+                        .map(|face| block.texture(face).and_then(&layer_of).unwrap_or_default()),
+                );
+            }
+        }
+
+        Self { layers }
+    }
+
+    /// Numbers textures in the order `BlockRegistry::texture_names` lists them.
+    pub fn from_registry(registry: &BlockRegistry) -> Self {
+        let names = registry.texture_names();
+        Self::new(registry, |texture| {
+            names
+                .iter()
+                .position(|&name| name == texture)
+                .map(|layer| layer as u32)
+        })
+    }
+
+    /// Returns None if the block isn't drawn.
+    pub fn layer(&self, block: BlockId, face: Face) -> Option<u32> {
+        self.layers
+            .get(block as usize)
+            .copied()
+            .flatten()
+            .map(|layers| layers[face.index()])
+    }
+}
@@ -0,0 +1,154 @@
+use ultraviolet as uv;
+use wgpu::{util::DeviceExt, Buffer, Device};
+
+use crate::{block::face::Face, primitives::vertex::Vertex, world::lighting::MAX_LIGHT};
+
+use super::mesher::FaceKey;
+
+/// Brightness of a vertex for each ambient occlusion level, from fully
+/// occluded to not occluded at all.
+const AMBIENT_OCCLUSION_CURVE: [f32; 4] = [0.4, 0.6, 0.8, 1.0];
+
+/// CPU-side geometry for a chunk, in world space.
+#[derive(Debug, Default, Clone)]
+pub struct ChunkMesh {
+    pub vertices: Vec<Vertex>,
+    pub indices: Vec<u32>,
+}
+
+impl ChunkMesh {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.indices.is_empty()
+    }
+
+    pub fn triangle_count(&self) -> usize {
+        self.indices.len() / 3
+    }
+
+    /// Adds a quad covering `size` blocks of a face, starting from the block
+    /// whose minimum corner is at `min`.
+    ///
+    /// `size` is measured along the face's horizontal then vertical axis, as
+    /// returned by `face_axes`. Texture coordinates run from 0 to `size` so
+    /// the texture repeats once per block.
+    pub fn push_quad(&mut self, face: Face, min: uv::Vec3, size: (f32, f32), key: FaceKey) {
+        let (u, v) = face_axes(face);
+        let (width, height) = size;
+
+        // Faces pointing along a positive axis lie on the far side of the block.
+        let normal = face.normal();
+        let tangent = face_tangent(face);
+        let mut base = min + normal.map(|n| n.max(0.0));
+        // The quad is wound from its bottom left corner as seen from outside
+        // the block, which is at the far end of any axis that runs backwards.
+        base -= u.map(|n| n.min(0.0)) * width + v.map(|n| n.min(0.0)) * height;
+
+        let first_index = self.vertices.len() as u32;
+        // 0,0______1,0
+        //  |        |
+        //  |        |
+        //  |________|
+        // 0,1      1,1
+        let corners = [
+            (base, uv::Vec2::new(0.0, height)),
+            (base + v * height, uv::Vec2::new(0.0, 0.0)),
+            (base + u * width + v * height, uv::Vec2::new(width, 0.0)),
+            (base + u * width, uv::Vec2::new(width, height)),
+        ];
+        for (corner, (position, tex_coords)) in corners.into_iter().enumerate() {
+            self.vertices.push(Vertex {
+                position,
+                tex_coords,
+                texture_layer: key.texture_layer,
+                ambient_occlusion: AMBIENT_OCCLUSION_CURVE[key.ambient_occlusion[corner] as usize],
+                normal,
+                light: uv::Vec2::new(
+                    key.sky_light[corner] as f32 / MAX_LIGHT as f32,
+                    key.block_light[corner] as f32 / MAX_LIGHT as f32,
+                ),
+                tangent,
+            });
+        }
+
+        // Split the quad along the diagonal between its two brightest corners.
+        // Otherwise a single dark corner bleeds across the whole quad.
+        let [a0, a1, a2, a3] = key.ambient_occlusion;
+        let order = if a1 + a3 > a0 + a2 {
+            [1, 2, 3, 1, 3, 0]
+        } else {
+            [0, 1, 2, 0, 2, 3]
+        };
+        // Both orders are clockwise, matching the pipeline's front face.
+        self.indices
+            .extend(order.iter().map(|corner| first_index + corner));
+    }
+}
+
+/// Directions of the horizontal and vertical edges of a face, as seen looking
+/// at it from outside the block.
+///
+/// The horizontal axis is always x, except for east and west faces where it's
+/// z. The vertical axis is y, except for top and bottom faces where it's z.
+pub fn face_axes(face: Face) -> (uv::Vec3, uv::Vec3) {
+    match face {
+        Face::East => (-uv::Vec3::unit_z(), uv::Vec3::unit_y()),
+        Face::West => (uv::Vec3::unit_z(), uv::Vec3::unit_y()),
+        Face::Top => (uv::Vec3::unit_x(), -uv::Vec3::unit_z()),
+        Face::Bottom => (uv::Vec3::unit_x(), uv::Vec3::unit_z()),
+        Face::South => (uv::Vec3::unit_x(), uv::Vec3::unit_y()),
+        Face::North => (-uv::Vec3::unit_x(), uv::Vec3::unit_y()),
+    }
+}
+
+/// Tangent of a face for normal mapping, pointing along its horizontal
+/// axis, the way texture coordinates increase across it.
+///
+/// The w component flips the bitangent worked out from the normal and
+/// tangent so that it points up the face, the way normal maps expect.
+pub fn face_tangent(face: Face) -> uv::Vec4 {
+    let (u, v) = face_axes(face);
+    let handedness = if face.normal().cross(u).dot(v) > 0.0 {
+        1.0
+    } else {
+        -1.0
+    };
+    uv::Vec4::new(u.x, u.y, u.z, handedness)
+}
+
+/// A chunk mesh uploaded to the GPU.
+pub struct ChunkMeshBuffers {
+    pub vertex_buffer: Buffer,
+    pub index_buffer: Buffer,
+    pub index_count: u32,
+}
+
+impl ChunkMeshBuffers {
+    /// Returns None for empty meshes, which have nothing to draw.
+    pub fn new(device: &Device, mesh: &ChunkMesh) -> Option<Self> {
+        if mesh.is_empty() {
+            return None;
+        }
+
+        let vertex_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Chunk Vertex Buffer"),
+            contents: bytemuck::cast_slice(&mesh.vertices),
+            usage: wgpu::BufferUsages::VERTEX,
+        });
+
+        let index_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Chunk Index Buffer"),
+            contents: bytemuck::cast_slice(&mesh.indices),
+            usage: wgpu::BufferUsages::INDEX,
+        });
+
+        Some(Self {
+            vertex_buffer,
+            index_buffer,
+            index_count: mesh.indices.len() as u32,
+        })
+    }
+}
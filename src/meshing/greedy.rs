@@ -0,0 +1,128 @@
+use ultraviolet as uv;
+
+use crate::{
+    block::face::Face,
+    world::{
+        chunk::{CHUNK_HEIGHT, CHUNK_WIDTH},
+        neighbourhood::ChunkNeighbourhood,
+    },
+};
+
+use super::{
+    chunk_mesh::ChunkMesh,
+    mesher::{FaceKey, Mesher},
+};
+
+/// Meshes a chunk one slice at a time, growing each visible face into the
+/// largest rectangle of identical faces it can, first along the face's
+/// horizontal axis and then along its vertical axis.
+pub fn mesh(mesher: &Mesher, chunks: &ChunkNeighbourhood) -> ChunkMesh {
+    let mut mesh = ChunkMesh::new();
+    let origin = chunks.pos.origin();
+
+    for face in Face::ALL {
+        let (slices, width, height) = slice_dimensions(face);
+        let mut mask: Vec<Option<FaceKey>> = vec![None; width * height];
+
+        for slice in 0..slices {
+            for v in 0..height {
+                for u in 0..width {
+                    let (x, y, z) = block_position(face, slice, u, v);
+                    mask[v * width + u] = mesher.visible_face(chunks, x, y, z, face);
+                }
+            }
+
+            for v in 0..height {
+                let mut u = 0;
+                while u < width {
+                    let Some(key) = mask[v * width + u] else {
+                        u += 1;
+                        continue;
+                    };
+
+                    let mut quad_width = 1;
+                    while u + quad_width < width && mask[v * width + u + quad_width] == Some(key) {
+                        quad_width += 1;
+                    }
+
+                    let mut quad_height = 1;
+                    'grow: while v + quad_height < height {
+                        let row = (v + quad_height) * width;
+                        for cell in &mask[row + u..row + u + quad_width] {
+                            if *cell != Some(key) {
+                                break 'grow;
+                            }
+                        }
+                        quad_height += 1;
+                    }
+
+                    for row in v..v + quad_height {
+                        mask[row * width + u..row * width + u + quad_width].fill(None);
+                    }
+
+                    let (x, y, z) = block_position(face, slice, u, v);
+                    let min = uv::Vec3::new(
+                        (origin.x + x as i32) as f32,
+                        y as f32,
+                        (origin.z + z as i32) as f32,
+                    );
+                    mesh.push_quad(face, min, (quad_width as f32, quad_height as f32), key);
+
+                    u += quad_width;
+                }
+            }
+        }
+    }
+
+    mesh
+}
+
+/// Number of slices through the chunk facing the same way as `face`, and the
+/// size of each slice along the face's horizontal and vertical axes.
+fn slice_dimensions(face: Face) -> (usize, usize, usize) {
+    match face {
+        Face::East | Face::West => (CHUNK_WIDTH, CHUNK_WIDTH, CHUNK_HEIGHT),
+        Face::Top | Face::Bottom => (CHUNK_HEIGHT, CHUNK_WIDTH, CHUNK_WIDTH),
+        Face::South | Face::North => (CHUNK_WIDTH, CHUNK_WIDTH, CHUNK_HEIGHT),
+    }
+}
+
+/// Converts a position within a slice into a local block position, using the
+/// same axes as `chunk_mesh::face_axes`.
+fn block_position(face: Face, slice: usize, u: usize, v: usize) -> (usize, usize, usize) {
+    match face {
+        Face::East | Face::West => (slice, v, u),
+        Face::Top | Face::Bottom => (u, slice, v),
+        Face::South | Face::North => (u, v, slice),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        meshing::mesher::{
+            tests::{checkerboard, layer, mesher, triangle_count, LAYER, STONE},
+            MeshingMode,
+        },
+        world::chunk::Chunk,
+    };
+
+    #[test]
+    fn flat_plane_becomes_one_quad_per_side() {
+        let chunk = layer(|_, _| STONE);
+        assert_eq!(triangle_count(&mesher(MeshingMode::Greedy), &chunk), 12);
+    }
+
+    #[test]
+    fn checkerboard_cannot_be_merged() {
+        let chunk = layer(checkerboard);
+        assert_eq!(triangle_count(&mesher(MeshingMode::Greedy), &chunk), 1152);
+    }
+
+    #[test]
+    fn single_block_draws_six_faces() {
+        let mut chunk = Chunk::new();
+        chunk.set_block(8, LAYER, 8, STONE);
+        assert_eq!(triangle_count(&mesher(MeshingMode::Greedy), &chunk), 12);
+    }
+}
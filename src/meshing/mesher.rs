@@ -0,0 +1,386 @@
+use ultraviolet as uv;
+
+use crate::{
+    block::{face::Face, registry::BlockRegistry},
+    world::{
+        chunk::{BlockId, AIR, CHUNK_HEIGHT, CHUNK_WIDTH},
+        lighting::{LightChannel, MAX_LIGHT},
+        neighbourhood::ChunkNeighbourhood,
+    },
+};
+
+use super::{
+    block_textures::BlockTextures,
+    chunk_mesh::{face_axes, ChunkMesh},
+    greedy,
+};
+
+/// Greedy meshing is the default, since terrain is mostly large flat areas
+/// that it draws with far fewer triangles. Culled meshing is kept for
+/// comparison, and for debugging the greedy mesher.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum MeshingMode {
+    /// One quad per visible block face.
+    Culled,
+    /// Merges neighbouring faces that look the same into larger quads.
+    #[default]
+    Greedy,
+}
+
+impl MeshingMode {
+    pub fn toggled(self) -> Self {
+        match self {
+            MeshingMode::Culled => MeshingMode::Greedy,
+            MeshingMode::Greedy => MeshingMode::Culled,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MesherSettings {
+    pub mode: MeshingMode,
+    /// Darkens the corners of faces next to other blocks.
+    pub ambient_occlusion: bool,
+    /// Blends light levels across faces by averaging them at each corner,
+    /// rather than lighting each face evenly.
+    pub smooth_lighting: bool,
+}
+
+impl Default for MesherSettings {
+    fn default() -> Self {
+        Self {
+            mode: MeshingMode::default(),
+            ambient_occlusion: true,
+            smooth_lighting: true,
+        }
+    }
+}
+
+/// Everything about a visible face that decides how it's drawn.
+///
+/// Greedy meshing only merges faces whose keys are equal.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FaceKey {
+    pub texture_layer: u32,
+    /// Ambient occlusion level at each corner of the face, from 0 (fully
+    /// occluded) to 3, in the order `ChunkMesh::push_quad` winds them.
+    pub ambient_occlusion: [u8; 4],
+    /// Sky and block light at each corner, from 0 to 15, in the same order.
+    pub sky_light: [u8; 4],
+    pub block_light: [u8; 4],
+}
+
+/// Builds chunk meshes, leaving out faces hidden by neighbouring blocks.
+#[derive(Clone)]
+pub struct Mesher {
+    textures: BlockTextures,
+    // Indexed by block ID.
+    opaque: Vec<bool>,
+    pub settings: MesherSettings,
+}
+
+impl Mesher {
+    pub fn new(
+        registry: &BlockRegistry,
+        textures: BlockTextures,
+        settings: MesherSettings,
+    ) -> Self {
+        let mut opaque = Vec::new();
+        for block in registry.blocks() {
+            let index = block.id as usize;
+            if index >= opaque.len() {
+                opaque.resize(index + 1, false);
+            }
+            opaque[index] = registry.is_opaque(block.id);
+        }
+
+        Self {
+            textures,
+            opaque,
+            settings,
+        }
+    }
+
+    pub fn mesh(&self, chunks: &ChunkNeighbourhood) -> ChunkMesh {
+        match self.settings.mode {
+            MeshingMode::Culled => self.mesh_culled(chunks),
+            MeshingMode::Greedy => greedy::mesh(self, chunks),
+        }
+    }
+
+    fn mesh_culled(&self, chunks: &ChunkNeighbourhood) -> ChunkMesh {
+        let mut mesh = ChunkMesh::new();
+        let origin = chunks.pos.origin();
+
+        for y in 0..CHUNK_HEIGHT {
+            for z in 0..CHUNK_WIDTH {
+                for x in 0..CHUNK_WIDTH {
+                    for face in Face::ALL {
+                        let Some(key) = self.visible_face(chunks, x, y, z, face) else {
+                            continue;
+                        };
+
+                        let min = uv::Vec3::new(
+                            (origin.x + x as i32) as f32,
+                            y as f32,
+                            (origin.z + z as i32) as f32,
+                        );
+                        mesh.push_quad(face, min, (1.0, 1.0), key);
+                    }
+                }
+            }
+        }
+
+        mesh
+    }
+
+    /// Returns the key for a face of the block at a position local to the
+    /// center chunk, or None if there's nothing to draw there.
+    pub fn visible_face(
+        &self,
+        chunks: &ChunkNeighbourhood,
+        x: usize,
+        y: usize,
+        z: usize,
+        face: Face,
+    ) -> Option<FaceKey> {
+        let block = chunks.center().get_block(x, y, z);
+        if block == AIR {
+            return None;
+        }
+        let texture_layer = self.textures.layer(block, face)?;
+
+        let offset = face.offset();
+        let neighbour = chunks.get_block(
+            x as i32 + offset.x,
+            y as i32 + offset.y,
+            z as i32 + offset.z,
+        );
+        if !self.is_face_visible(block, face, y, neighbour) {
+            return None;
+        }
+
+        let ambient_occlusion = if self.settings.ambient_occlusion {
+            self.ambient_occlusion(chunks, x, y, z, face)
+        } else {
+            [3; 4]
+        };
+
+        Some(FaceKey {
+            texture_layer,
+            ambient_occlusion,
+            sky_light: self.face_light(chunks, x, y, z, face, LightChannel::Sky),
+            block_light: self.face_light(chunks, x, y, z, face, LightChannel::Block),
+        })
+    }
+
+    /// Light reaching each corner of a face, taken from the blocks in front
+    /// of it.
+    ///
+    /// With smooth lighting, each corner averages the block in front of the
+    /// face with the three around the corner, skipping opaque ones. The
+    /// diagonal block is also skipped when both sides are opaque, since light
+    /// couldn't get around to it.
+    fn face_light(
+        &self,
+        chunks: &ChunkNeighbourhood,
+        x: usize,
+        y: usize,
+        z: usize,
+        face: Face,
+        channel: LightChannel,
+    ) -> [u8; 4] {
+        let front = uv::IVec3::new(x as i32, y as i32, z as i32) + face.offset();
+        // Faces on the edge of the loaded world are lit as if they were
+        // outside, until their neighbour arrives and they're meshed again.
+        let unloaded = match channel {
+            LightChannel::Sky => MAX_LIGHT,
+            LightChannel::Block => 0,
+        };
+        let front_light = chunks
+            .get_light(front.x, front.y, front.z, channel)
+            .unwrap_or(unloaded);
+        if !self.settings.smooth_lighting {
+            return [front_light; 4];
+        }
+
+        let (u, v) = face_axes(face);
+        let u = uv::IVec3::new(u.x as i32, u.y as i32, u.z as i32);
+        let v = uv::IVec3::new(v.x as i32, v.y as i32, v.z as i32);
+        // The light at a position, or None if it's opaque or not loaded.
+        let light_at = |pos: uv::IVec3| {
+            let block = chunks.get_block(pos.x, pos.y, pos.z)?;
+            if self.is_opaque(block) {
+                return None;
+            }
+            chunks.get_light(pos.x, pos.y, pos.z, channel)
+        };
+
+        [(-1, -1), (-1, 1), (1, 1), (1, -1)].map(|(du, dv)| {
+            let side_u = light_at(front + u * du);
+            let side_v = light_at(front + v * dv);
+            let corner = if side_u.is_some() || side_v.is_some() {
+                light_at(front + u * du + v * dv)
+            } else {
+                None
+            };
+
+            let samples = [Some(front_light), side_u, side_v, corner];
+            let (total, count) = samples
+                .iter()
+                .flatten()
+                .fold((0, 0), |(total, count), &light| {
+                    (total + light as u32, count + 1)
+                });
+            ((total + count / 2) / count) as u8
+        })
+    }
+
+    /// Samples the blocks in front of a face around each of its corners.
+    ///
+    /// A corner is darker the more of its two side blocks and one diagonal
+    /// block are opaque, and fully dark when both sides are.
+    fn ambient_occlusion(
+        &self,
+        chunks: &ChunkNeighbourhood,
+        x: usize,
+        y: usize,
+        z: usize,
+        face: Face,
+    ) -> [u8; 4] {
+        let front = uv::IVec3::new(x as i32, y as i32, z as i32) + face.offset();
+        let (u, v) = face_axes(face);
+        let u = uv::IVec3::new(u.x as i32, u.y as i32, u.z as i32);
+        let v = uv::IVec3::new(v.x as i32, v.y as i32, v.z as i32);
+        let occludes = |pos: uv::IVec3| {
+            chunks
+                .get_block(pos.x, pos.y, pos.z)
+                .is_some_and(|block| self.is_opaque(block))
+        };
+
+        // Corners in winding order: bottom left, top left, top right, bottom right.
+        [(-1, -1), (-1, 1), (1, 1), (1, -1)].map(|(du, dv)| {
+            let side_u = occludes(front + u * du);
+            let side_v = occludes(front + v * dv);
+            let corner = occludes(front + u * du + v * dv);
+            if side_u && side_v {
+                0
+            } else {
+                3 - (side_u as u8 + side_v as u8 + corner as u8)
+            }
+        })
+    }
+
+    /// Whether a face of `block` can be seen past the `neighbour` it touches.
+    fn is_face_visible(
+        &self,
+        block: BlockId,
+        face: Face,
+        y: usize,
+        neighbour: Option<BlockId>,
+    ) -> bool {
+        match neighbour {
+            // Nothing can see the bottom of the world.
+            None if face == Face::Bottom && y == 0 => false,
+            // Faces on the edge of the loaded world stay visible.
+            None => true,
+            // Neighbouring transparent blocks of the same type, like water,
+            // merge together without faces between them.
+            Some(neighbour) => !self.is_opaque(neighbour) && neighbour != block,
+        }
+    }
+
+    fn is_opaque(&self, block: BlockId) -> bool {
+        self.opaque.get(block as usize).copied().unwrap_or(false)
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use crate::world::chunk::{Chunk, ChunkPos};
+
+    use super::*;
+
+    pub(crate) const STONE: BlockId = 1;
+    pub(crate) const DIRT: BlockId = 2;
+    /// Height of the layers built by the tests, clear of the bottom of the
+    /// world so their bottom faces are drawn.
+    pub(crate) const LAYER: usize = 64;
+
+    pub(crate) fn registry() -> BlockRegistry {
+        let definitions = ron::from_str(
+            r#"#![enable(implicit_some)]
+            [
+                (id: 1, name: "stone", textures: All("stone")),
+                (id: 2, name: "dirt", textures: All("dirt")),
+            ]"#,
+        )
+        .unwrap();
+        BlockRegistry::from_definitions(definitions, |_| true).unwrap()
+    }
+
+    pub(crate) fn mesher(mode: MeshingMode) -> Mesher {
+        let registry = registry();
+        let settings = MesherSettings {
+            mode,
+            ..MesherSettings::default()
+        };
+        Mesher::new(&registry, BlockTextures::from_registry(&registry), settings)
+    }
+
+    /// Meshes a chunk with no neighbours loaded, so faces on its edges are
+    /// drawn.
+    pub(crate) fn triangle_count(mesher: &Mesher, chunk: &Chunk) -> usize {
+        let chunks = ChunkNeighbourhood::new(ChunkPos::new(0, 0), chunk, |_, _| None);
+        mesher.mesh(&chunks).triangle_count()
+    }
+
+    /// A single layer of blocks covering the chunk, choosing each block by
+    /// its position.
+    pub(crate) fn layer(block: impl Fn(usize, usize) -> BlockId) -> Chunk {
+        let mut chunk = Chunk::new();
+        for z in 0..CHUNK_WIDTH {
+            for x in 0..CHUNK_WIDTH {
+                chunk.set_block(x, LAYER, z, block(x, z));
+            }
+        }
+        chunk
+    }
+
+    pub(crate) fn checkerboard(x: usize, z: usize) -> BlockId {
+        if (x + z).is_multiple_of(2) {
+            STONE
+        } else {
+            DIRT
+        }
+    }
+
+    #[test]
+    fn culled_flat_plane_draws_every_exposed_face() {
+        // The top and bottom of all 256 blocks, plus 16 faces on each edge.
+        let chunk = layer(|_, _| STONE);
+        assert_eq!(triangle_count(&mesher(MeshingMode::Culled), &chunk), 1152);
+    }
+
+    #[test]
+    fn culled_checkerboard_matches_flat_plane() {
+        let chunk = layer(checkerboard);
+        assert_eq!(triangle_count(&mesher(MeshingMode::Culled), &chunk), 1152);
+    }
+
+    #[test]
+    fn culled_single_block_draws_six_faces() {
+        let mut chunk = Chunk::new();
+        chunk.set_block(8, LAYER, 8, STONE);
+        assert_eq!(triangle_count(&mesher(MeshingMode::Culled), &chunk), 12);
+    }
+
+    #[test]
+    fn faces_between_blocks_and_at_the_bottom_of_the_world_are_hidden() {
+        let mut chunk = Chunk::new();
+        chunk.set_block(8, 0, 8, STONE);
+        chunk.set_block(9, 0, 8, STONE);
+        // Two tops and the four sides of each block not facing the other.
+        assert_eq!(triangle_count(&mesher(MeshingMode::Culled), &chunk), 16);
+    }
+}
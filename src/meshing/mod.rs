@@ -0,0 +1,4 @@
+pub mod block_textures;
+pub mod chunk_mesh;
+mod greedy;
+pub mod mesher;
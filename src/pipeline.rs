@@ -1,35 +1,143 @@
 use wgpu::{PipelineLayoutDescriptor, RenderPipelineDescriptor};
 
-use crate::{camera::camera_uniform::CameraUniform, primitives::vertex::Vertex, texture::Texture};
+use crate::{
+    camera::camera_uniform::CameraUniform,
+    depth_texture::DEPTH_FORMAT,
+    light::{light_uniform::LightUniform, shadow_map::ShadowMap},
+    primitives::{instance::InstanceRaw, vertex::Vertex},
+};
 
 pub fn create_pipeline_layout(
     device: &wgpu::Device,
     camera: &CameraUniform,
-    texture: Option<&Texture>,
+    texture_layout: &wgpu::BindGroupLayout,
+    light: &LightUniform,
+    shadow_map: &ShadowMap,
 ) -> wgpu::PipelineLayout {
     device.create_pipeline_layout(&PipelineLayoutDescriptor {
         label: None,
         bind_group_layouts: &[
-            &texture.unwrap().get_bind_group_layout(&device),
+            texture_layout,
             &camera.layout,
+            &light.layout,
+            &shadow_map.layout,
         ],
         push_constant_ranges: &[],
     })
 }
 
+/// Layout for the shadow pipeline, which only needs the light view
+/// projection of the cascade being drawn.
+pub fn create_shadow_pipeline_layout(
+    device: &wgpu::Device,
+    shadow_map: &ShadowMap,
+) -> wgpu::PipelineLayout {
+    device.create_pipeline_layout(&PipelineLayoutDescriptor {
+        label: Some("Shadow Pipeline Layout"),
+        bind_group_layouts: &[&shadow_map.cascade_layout],
+        push_constant_ranges: &[],
+    })
+}
+
 pub fn create_render_pipeline(
     device: &wgpu::Device,
     pipeline_layout: &wgpu::PipelineLayout,
     shader: &wgpu::ShaderModule,
     swapchain_format: wgpu::TextureFormat,
+) -> wgpu::RenderPipeline {
+    build_render_pipeline(
+        device,
+        "Render Pipeline",
+        pipeline_layout,
+        shader,
+        swapchain_format,
+        &[Vertex::get_buffer_layout()],
+    )
+}
+
+/// A pipeline drawing one mesh many times, taking a second vertex buffer of
+/// `InstanceRaw`s in slot 1.
+pub fn create_instanced_render_pipeline(
+    device: &wgpu::Device,
+    pipeline_layout: &wgpu::PipelineLayout,
+    shader: &wgpu::ShaderModule,
+    swapchain_format: wgpu::TextureFormat,
+) -> wgpu::RenderPipeline {
+    build_render_pipeline(
+        device,
+        "Instanced Render Pipeline",
+        pipeline_layout,
+        shader,
+        swapchain_format,
+        &[
+            Vertex::get_buffer_layout(),
+            InstanceRaw::get_buffer_layout(),
+        ],
+    )
+}
+
+/// A depth-only pipeline drawing chunk meshes from the light's point of view
+/// into a shadow map cascade.
+pub fn create_shadow_pipeline(
+    device: &wgpu::Device,
+    pipeline_layout: &wgpu::PipelineLayout,
+    shader: &wgpu::ShaderModule,
 ) -> wgpu::RenderPipeline {
     device.create_render_pipeline(&RenderPipelineDescriptor {
-        label: Some("Render Pipeline"),
-        layout: Some(&pipeline_layout),
+        label: Some("Shadow Pipeline"),
+        layout: Some(pipeline_layout),
         vertex: wgpu::VertexState {
             module: shader,
             entry_point: "vs_main",
-            buffers: &[Vertex::get_buffer_layout()], // What type of vertices to pass to the vertex shader.
+            buffers: &[Vertex::get_buffer_layout()],
+        },
+        // Only depth is written, so there's nothing for a fragment shader to do.
+        fragment: None,
+        primitive: wgpu::PrimitiveState {
+            topology: wgpu::PrimitiveTopology::TriangleList,
+            strip_index_format: None,
+            front_face: wgpu::FrontFace::Cw,
+            // Chunk meshes leave out faces hidden inside the terrain, so
+            // culling back faces would let light leak through at the edges
+            // of the loaded world.
+            cull_mode: None,
+            polygon_mode: wgpu::PolygonMode::Fill,
+            unclipped_depth: false,
+            conservative: false,
+        },
+        depth_stencil: Some(wgpu::DepthStencilState {
+            format: DEPTH_FORMAT,
+            depth_write_enabled: true,
+            depth_compare: wgpu::CompareFunction::Less,
+            stencil: wgpu::StencilState::default(),
+            // Pushes depths away from the light so surfaces don't shadow
+            // themselves, more so for surfaces at a steep angle to it.
+            bias: wgpu::DepthBiasState {
+                constant: 2,
+                slope_scale: 2.0,
+                clamp: 0.0,
+            },
+        }),
+        multisample: wgpu::MultisampleState::default(),
+        multiview: None,
+    })
+}
+
+fn build_render_pipeline(
+    device: &wgpu::Device,
+    label: &str,
+    pipeline_layout: &wgpu::PipelineLayout,
+    shader: &wgpu::ShaderModule,
+    swapchain_format: wgpu::TextureFormat,
+    buffers: &[wgpu::VertexBufferLayout],
+) -> wgpu::RenderPipeline {
+    device.create_render_pipeline(&RenderPipelineDescriptor {
+        label: Some(label),
+        layout: Some(pipeline_layout),
+        vertex: wgpu::VertexState {
+            module: shader,
+            entry_point: "vs_main",
+            buffers, // What type of vertices to pass to the vertex shader.
         },
         fragment: Some(wgpu::FragmentState {
             module: shader,
@@ -52,7 +160,14 @@ pub fn create_render_pipeline(
             // Requires Features::CONSERVATIVE_RASTERIZATION
             conservative: false,
         },
-        depth_stencil: None,
+        depth_stencil: Some(wgpu::DepthStencilState {
+            format: DEPTH_FORMAT,
+            depth_write_enabled: true,
+            // Only draw fragments closer than whatever has already been drawn there.
+            depth_compare: wgpu::CompareFunction::Less,
+            stencil: wgpu::StencilState::default(),
+            bias: wgpu::DepthBiasState::default(),
+        }),
         multisample: wgpu::MultisampleState {
             count: 1,
             mask: !0, // Used to determine which samples should be active, this means all of them
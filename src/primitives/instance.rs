@@ -0,0 +1,137 @@
+use bytemuck_derive::{Pod, Zeroable};
+use ultraviolet as uv;
+use wgpu::{util::DeviceExt, Buffer, Device, Queue, VertexBufferLayout};
+
+/// One copy of a mesh drawn with instancing, e.g. an entity or a debug
+/// marker.
+#[derive(Debug, Clone, Copy)]
+pub struct Instance {
+    pub position: uv::Vec3,
+    pub rotation: uv::Rotor3,
+    pub scale: f32,
+    /// Multiplied with the texture colour, including alpha.
+    pub tint: uv::Vec4,
+    pub texture_layer: u32,
+}
+
+impl Instance {
+    pub fn new(position: uv::Vec3) -> Self {
+        Self {
+            position,
+            rotation: uv::Rotor3::identity(),
+            scale: 1.0,
+            tint: uv::Vec4::one(),
+            texture_layer: 0,
+        }
+    }
+
+    pub fn to_raw(&self) -> InstanceRaw {
+        let model = uv::Mat4::from_translation(self.position)
+            * self.rotation.into_matrix().into_homogeneous()
+            * uv::Mat4::from_scale(self.scale);
+        InstanceRaw {
+            model,
+            tint: self.tint,
+            texture_layer: self.texture_layer,
+        }
+    }
+}
+
+/// An instance as laid out in the instance buffer.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Pod, Zeroable)]
+pub struct InstanceRaw {
+    pub model: uv::Mat4,
+    pub tint: uv::Vec4,
+    pub texture_layer: u32,
+}
+
+impl InstanceRaw {
+    /// Instances start at location 8, leaving room for more per-vertex
+    /// attributes in `Vertex`.
+    pub fn get_buffer_layout() -> VertexBufferLayout<'static> {
+        VertexBufferLayout {
+            array_stride: std::mem::size_of::<InstanceRaw>() as wgpu::BufferAddress,
+            // Step forward once per instance rather than once per vertex.
+            step_mode: wgpu::VertexStepMode::Instance,
+            attributes: &[
+                // A mat4 takes up four locations, one per column.
+                wgpu::VertexAttribute {
+                    offset: 0,
+                    shader_location: 8,
+                    format: wgpu::VertexFormat::Float32x4,
+                },
+                wgpu::VertexAttribute {
+                    offset: std::mem::size_of::<[f32; 4]>() as wgpu::BufferAddress,
+                    shader_location: 9,
+                    format: wgpu::VertexFormat::Float32x4,
+                },
+                wgpu::VertexAttribute {
+                    offset: std::mem::size_of::<[f32; 8]>() as wgpu::BufferAddress,
+                    shader_location: 10,
+                    format: wgpu::VertexFormat::Float32x4,
+                },
+                wgpu::VertexAttribute {
+                    offset: std::mem::size_of::<[f32; 12]>() as wgpu::BufferAddress,
+                    shader_location: 11,
+                    format: wgpu::VertexFormat::Float32x4,
+                },
+                wgpu::VertexAttribute {
+                    offset: std::mem::size_of::<[f32; 16]>() as wgpu::BufferAddress,
+                    shader_location: 12,
+                    format: wgpu::VertexFormat::Float32x4,
+                },
+                wgpu::VertexAttribute {
+                    offset: std::mem::size_of::<[f32; 20]>() as wgpu::BufferAddress,
+                    shader_location: 13,
+                    format: wgpu::VertexFormat::Uint32,
+                },
+            ],
+        }
+    }
+}
+
+/// Instances uploaded to the GPU, drawn with `0..count` as the instance range.
+pub struct InstanceBuffer {
+    pub buffer: Buffer,
+    pub count: u32,
+    capacity: usize,
+}
+
+impl InstanceBuffer {
+    pub fn new(device: &Device, instances: &[Instance]) -> Self {
+        let raw: Vec<InstanceRaw> = instances.iter().map(Instance::to_raw).collect();
+        Self {
+            buffer: Self::create_buffer(device, &raw),
+            count: raw.len() as u32,
+            capacity: raw.len(),
+        }
+    }
+
+    /// Replaces the instances, only creating a new buffer if the old one is
+    /// too small.
+    pub fn update(&mut self, device: &Device, queue: &Queue, instances: &[Instance]) {
+        let raw: Vec<InstanceRaw> = instances.iter().map(Instance::to_raw).collect();
+        if raw.len() > self.capacity {
+            self.buffer = Self::create_buffer(device, &raw);
+            self.capacity = raw.len();
+        } else if !raw.is_empty() {
+            queue.write_buffer(&self.buffer, 0, bytemuck::cast_slice(&raw));
+        }
+        self.count = raw.len() as u32;
+    }
+
+    fn create_buffer(device: &Device, raw: &[InstanceRaw]) -> Buffer {
+        // Buffers can't be empty, so there's always room for at least one.
+        let contents = if raw.is_empty() {
+            vec![bytemuck::Zeroable::zeroed()]
+        } else {
+            raw.to_vec()
+        };
+        device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Instance Buffer"),
+            contents: bytemuck::cast_slice(&contents),
+            usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+        })
+    }
+}
@@ -7,13 +7,28 @@ use wgpu::VertexBufferLayout;
 pub struct Vertex {
     pub position: uv::Vec3,
     pub tex_coords: uv::Vec2,
+    pub texture_layer: u32,
+    /// How much ambient light reaches the vertex, from 0 to 1.
+    pub ambient_occlusion: f32,
+    pub normal: uv::Vec3,
+    /// Sky and block light reaching the vertex, from 0 to 1.
+    pub light: uv::Vec2,
+    /// Direction texture coordinates run across the surface, for normal
+    /// mapping. w is 1 or -1, flipping the bitangent worked out from the
+    /// normal and tangent so it points up the texture.
+    pub tangent: uv::Vec4,
 }
 
 impl Vertex {
-    pub fn new(pos: (f32, f32, f32), tex: (f32, f32)) -> Self {
+    pub fn new(pos: (f32, f32, f32), tex: (f32, f32), normal: (f32, f32, f32)) -> Self {
         Self {
             position: uv::Vec3::new(pos.0, pos.1, pos.2),
-            tex_coords: uv::Vec2::new(tex.0, tex.1)
+            tex_coords: uv::Vec2::new(tex.0, tex.1),
+            texture_layer: 0,
+            ambient_occlusion: 1.0,
+            normal: uv::Vec3::new(normal.0, normal.1, normal.2),
+            light: uv::Vec2::new(1.0, 0.0),
+            tangent: uv::Vec4::new(1.0, 0.0, 0.0, 1.0),
         }
     }
 
@@ -30,8 +45,33 @@ impl Vertex {
                 wgpu::VertexAttribute {
                     offset: std::mem::size_of::<[f32; 3]>() as wgpu::BufferAddress,
                     shader_location: 1,
+                    format: wgpu::VertexFormat::Float32x2,
+                },
+                wgpu::VertexAttribute {
+                    offset: std::mem::size_of::<[f32; 5]>() as wgpu::BufferAddress,
+                    shader_location: 2,
+                    format: wgpu::VertexFormat::Uint32,
+                },
+                wgpu::VertexAttribute {
+                    offset: std::mem::size_of::<[f32; 6]>() as wgpu::BufferAddress,
+                    shader_location: 3,
+                    format: wgpu::VertexFormat::Float32,
+                },
+                wgpu::VertexAttribute {
+                    offset: std::mem::size_of::<[f32; 7]>() as wgpu::BufferAddress,
+                    shader_location: 4,
                     format: wgpu::VertexFormat::Float32x3,
                 },
+                wgpu::VertexAttribute {
+                    offset: std::mem::size_of::<[f32; 10]>() as wgpu::BufferAddress,
+                    shader_location: 5,
+                    format: wgpu::VertexFormat::Float32x2,
+                },
+                wgpu::VertexAttribute {
+                    offset: std::mem::size_of::<[f32; 12]>() as wgpu::BufferAddress,
+                    shader_location: 6,
+                    format: wgpu::VertexFormat::Float32x4,
+                },
             ],
             // Attributes can be specified more concisely:
             // attributes: &wgpu::vertex_attr_array![0 => Float32x3, 1 => Float32x3],
@@ -0,0 +1,305 @@
+use std::{fmt, fs};
+
+use log::info;
+use serde::{Deserialize, Serialize};
+
+use crate::{game_loop, input::tick_input::TickInput, simulation::Simulation};
+
+/// Where each simulation step's input comes from.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub enum InputMode {
+    /// From the player.
+    #[default]
+    Live,
+    /// From the player, saving it to a file once the game closes.
+    Record(String),
+    /// From a recording, going back to the player once it runs out.
+    Replay(String),
+}
+
+/// Every step's input from a play session, along with what's needed to
+/// start another one the same way.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Recording {
+    /// The world seed. Replaying in a window generates the world from it,
+    /// but the simulation doesn't depend on the world, so `replay` has no
+    /// use for it.
+    pub seed: u64,
+    pub ticks_per_second: u32,
+    pub ticks: Vec<TickInput>,
+}
+
+impl Recording {
+    pub fn new(seed: u64, ticks_per_second: u32) -> Self {
+        Self {
+            seed,
+            ticks_per_second,
+            ticks: Vec::new(),
+        }
+    }
+
+    pub fn load(path: &str) -> Result<Self, ReplayError> {
+        info!("Loading recording from {}", path);
+        let source = fs::read_to_string(path).map_err(|error| ReplayError::Io {
+            path: path.to_string(),
+            error,
+        })?;
+        ron::from_str(&source).map_err(|error| ReplayError::Parse {
+            path: path.to_string(),
+            error,
+        })
+    }
+
+    pub fn save(&self, path: &str) -> Result<(), ReplayError> {
+        info!("Saving {} ticks of input to {}", self.ticks.len(), path);
+        // Each step gets a line of its own, so recordings can be read and
+        // compared a step at a time.
+        let config = ron::ser::PrettyConfig::default().depth_limit(2);
+        let source =
+            ron::ser::to_string_pretty(self, config).map_err(|error| ReplayError::Serialize {
+                path: path.to_string(),
+                error,
+            })?;
+        fs::write(path, source).map_err(|error| ReplayError::Io {
+            path: path.to_string(),
+            error,
+        })
+    }
+
+    /// Steps a new simulation through every tick, without a window or a
+    /// world.
+    pub fn replay(&self, aspect: f32) -> Simulation {
+        let mut simulation = Simulation::new(aspect);
+        let delta_time = game_loop::step_length(self.ticks_per_second).as_secs_f32();
+        for input in &self.ticks {
+            simulation.step(input, delta_time);
+        }
+        simulation
+    }
+}
+
+/// Hands out each simulation step's input, recording or replaying it
+/// depending on the input mode.
+pub struct InputSession {
+    seed: u64,
+    ticks_per_second: u32,
+    /// Where the player's input is going, if it's being recorded.
+    recording: Option<(String, Recording)>,
+    /// Recorded input still to be played back.
+    replay: std::vec::IntoIter<TickInput>,
+}
+
+impl InputSession {
+    /// Starts handing out input. A replay brings its own seed and tick rate,
+    /// while otherwise the ones given are used.
+    pub fn start(mode: InputMode, seed: u64, ticks_per_second: u32) -> Result<Self, ReplayError> {
+        let mut session = Self {
+            seed,
+            ticks_per_second,
+            recording: None,
+            replay: Vec::new().into_iter(),
+        };
+        match mode {
+            InputMode::Live => (),
+            InputMode::Record(path) => {
+                session.recording = Some((path, Recording::new(seed, ticks_per_second)))
+            }
+            InputMode::Replay(path) => {
+                let recording = Recording::load(&path)?;
+                session.seed = recording.seed;
+                session.ticks_per_second = recording.ticks_per_second;
+                session.replay = recording.ticks.into_iter();
+            }
+        }
+        Ok(session)
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    pub fn ticks_per_second(&self) -> u32 {
+        self.ticks_per_second
+    }
+
+    pub fn replaying(&self) -> bool {
+        self.replay.len() > 0
+    }
+
+    /// The input for the next step. While replaying, the player's `live`
+    /// input is ignored.
+    pub fn next_tick(&mut self, live: TickInput) -> TickInput {
+        if let Some(input) = self.replay.next() {
+            if self.replay.len() == 0 {
+                info!("Replay finished");
+            }
+            return input;
+        }
+        if let Some((_, recording)) = &mut self.recording {
+            recording.ticks.push(live.clone());
+        }
+        live
+    }
+
+    /// Saves the recording, if there is one. Recording stops afterwards.
+    pub fn finish(&mut self) -> Result<(), ReplayError> {
+        match self.recording.take() {
+            Some((path, recording)) => recording.save(&path),
+            None => Ok(()),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum ReplayError {
+    Io {
+        path: String,
+        error: std::io::Error,
+    },
+    Parse {
+        path: String,
+        error: ron::error::SpannedError,
+    },
+    Serialize {
+        path: String,
+        error: ron::Error,
+    },
+}
+
+impl fmt::Display for ReplayError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ReplayError::Io { path, error } => {
+                write!(f, "failed to access recording at {}: {}", path, error)
+            }
+            ReplayError::Parse { path, error } => {
+                write!(f, "failed to parse recording at {}: {}", path, error)
+            }
+            ReplayError::Serialize { path, error } => {
+                write!(f, "failed to write recording to {}: {}", path, error)
+            }
+        }
+    }
+}
+
+impl std::error::Error for ReplayError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ReplayError::Io { error, .. } => Some(error),
+            ReplayError::Parse { error, .. } => Some(error),
+            ReplayError::Serialize { error, .. } => Some(error),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use winit::event::VirtualKeyCode;
+
+    use crate::input::{
+        bindings::Bindings,
+        gamepad::{FakeGamepadBackend, GamepadAxis, GamepadId},
+        input_manager::InputManager,
+    };
+
+    use super::*;
+
+    const SEED: u64 = 42;
+    const TICKS_PER_SECOND: u32 = 60;
+    const TICKS: usize = 180;
+    const ASPECT: f32 = 16.0 / 9.0;
+
+    fn recording_path(name: &str) -> String {
+        let file = format!("{}-{}.ron", name, std::process::id());
+        let path: PathBuf = std::env::temp_dir().join(file);
+        path.to_string_lossy().into_owned()
+    }
+
+    /// Plays through a fixed script of key presses, mouse movement and an
+    /// analogue stick, the way the game loop does, recording to `path`.
+    fn play_live(path: &str) -> Simulation {
+        let bindings = Bindings::load("src/assets/bindings.ron").unwrap();
+        let mut input_manager = InputManager::new(bindings);
+        let gamepad = FakeGamepadBackend::new();
+        input_manager.set_gamepad_backend(Box::new(gamepad.clone()));
+        let pad = GamepadId(0);
+        gamepad.connect(pad);
+
+        let mut session =
+            InputSession::start(InputMode::Record(path.to_string()), SEED, TICKS_PER_SECOND)
+                .unwrap();
+        let mut simulation = Simulation::new(ASPECT);
+        let delta_time = game_loop::step_length(TICKS_PER_SECOND).as_secs_f32();
+        for tick in 0..TICKS {
+            match tick {
+                0 => input_manager.set_key_state(VirtualKeyCode::W, true),
+                30 | 120 => input_manager.set_key_state(VirtualKeyCode::C, true),
+                31 | 121 => input_manager.set_key_state(VirtualKeyCode::C, false),
+                60 => {
+                    input_manager.set_key_state(VirtualKeyCode::W, false);
+                    gamepad.set_axis(pad, GamepadAxis::LeftStickX, 0.7);
+                }
+                150 => gamepad.set_axis(pad, GamepadAxis::LeftStickX, 0.0),
+                _ => (),
+            }
+            if (40..100).contains(&tick) {
+                input_manager.add_mouse_motion((1.37, -0.61));
+            }
+            input_manager.poll_gamepads();
+
+            let input = session.next_tick(input_manager.take_tick_input());
+            simulation.step(&input, delta_time);
+            input_manager.end_frame();
+        }
+        session.finish().unwrap();
+        simulation
+    }
+
+    fn assert_same_state(simulation: &Simulation, expected: &Simulation) {
+        assert_eq!(simulation.tick(), expected.tick());
+        assert_eq!(simulation.first_person(), expected.first_person());
+        let (camera, expected) = (simulation.camera(), expected.camera());
+        assert_eq!(camera.eye, expected.eye);
+        assert_eq!(camera.target, expected.target);
+        assert_eq!(camera.up, expected.up);
+    }
+
+    #[test]
+    fn a_saved_recording_replays_to_the_same_state() {
+        let path = recording_path("round-trip");
+        let live = play_live(&path);
+        let recording = Recording::load(&path);
+        fs::remove_file(&path).unwrap();
+        let recording = recording.unwrap();
+
+        assert_eq!(recording.seed, SEED);
+        assert_eq!(recording.ticks_per_second, TICKS_PER_SECOND);
+        assert_eq!(recording.ticks.len(), TICKS);
+        assert_ne!(live.camera().eye, Simulation::new(ASPECT).camera().eye);
+        assert_same_state(&recording.replay(ASPECT), &live);
+    }
+
+    #[test]
+    fn a_replaying_session_ignores_live_input() {
+        let path = recording_path("session");
+        let live = play_live(&path);
+        let session = InputSession::start(InputMode::Replay(path.clone()), 0, 1);
+        fs::remove_file(&path).unwrap();
+        let mut session = session.unwrap();
+        assert_eq!(session.seed(), SEED);
+        assert_eq!(session.ticks_per_second(), TICKS_PER_SECOND);
+
+        let mut simulation = Simulation::new(ASPECT);
+        let delta_time = game_loop::step_length(session.ticks_per_second()).as_secs_f32();
+        let noise = TickInput {
+            mouse_delta: (100.0, 100.0),
+            ..TickInput::default()
+        };
+        while session.replaying() {
+            simulation.step(&session.next_tick(noise.clone()), delta_time);
+        }
+        assert_same_state(&simulation, &live);
+    }
+}
@@ -0,0 +1,115 @@
+use bytemuck_derive::{Pod, Zeroable};
+use wgpu::{util::DeviceExt, Device, Queue};
+
+pub const MAX_ANISOTROPY: u16 = 16;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SamplerSettings {
+    /// Filtering when a texel covers more than a pixel.
+    pub mag_filter: wgpu::FilterMode,
+    /// Filtering when a texel covers less than a pixel.
+    pub min_filter: wgpu::FilterMode,
+    /// Filtering between mip levels.
+    pub mipmap_filter: wgpu::FilterMode,
+    /// Up to 16. Anything above 1 needs linear filtering throughout, so it
+    /// overrides the filters above.
+    pub anisotropy: u16,
+    /// Added to the mip level the GPU picks. Negative values keep textures
+    /// sharper in the distance, at the cost of shimmering.
+    pub lod_bias: f32,
+}
+
+impl Default for SamplerSettings {
+    fn default() -> Self {
+        Self {
+            // Keeps block textures crisp up close.
+            mag_filter: wgpu::FilterMode::Nearest,
+            min_filter: wgpu::FilterMode::Nearest,
+            mipmap_filter: wgpu::FilterMode::Linear,
+            anisotropy: 1,
+            lod_bias: 0.0,
+        }
+    }
+}
+
+/// Sampler settings as laid out in the shader's uniform buffer.
+///
+/// Samplers have no LOD bias of their own, so the shader applies it.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Pod, Zeroable)]
+struct SamplerSettingsUniform {
+    lod_bias: f32,
+    // Uniforms are laid out in 16 byte blocks.
+    _padding: [f32; 3],
+}
+
+/// A texture sampler along with the settings the shader needs to match it.
+pub struct Sampler {
+    pub sampler: wgpu::Sampler,
+    pub settings_buffer: wgpu::Buffer,
+    settings: SamplerSettings,
+}
+
+impl Sampler {
+    pub fn new(device: &Device, settings: SamplerSettings) -> Self {
+        let settings_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Sampler Settings Buffer"),
+            contents: bytemuck::cast_slice(&[Self::uniform(&settings)]),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+
+        Self {
+            sampler: Self::create_sampler(device, &settings),
+            settings_buffer,
+            settings,
+        }
+    }
+
+    pub fn settings(&self) -> SamplerSettings {
+        self.settings
+    }
+
+    /// Applies new settings. The sampler is recreated, so bind groups using
+    /// it have to be too.
+    pub fn update(&mut self, device: &Device, queue: &Queue, settings: SamplerSettings) {
+        self.sampler = Self::create_sampler(device, &settings);
+        queue.write_buffer(
+            &self.settings_buffer,
+            0,
+            bytemuck::cast_slice(&[Self::uniform(&settings)]),
+        );
+        self.settings = settings;
+    }
+
+    fn create_sampler(device: &Device, settings: &SamplerSettings) -> wgpu::Sampler {
+        let anisotropy = settings.anisotropy.clamp(1, MAX_ANISOTROPY);
+        let filter = |filter| {
+            if anisotropy > 1 {
+                wgpu::FilterMode::Linear
+            } else {
+                filter
+            }
+        };
+
+        device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("Texture Sampler"),
+            // Greedy meshing stretches faces over several blocks and relies on
+            // the texture repeating across them.
+            address_mode_u: wgpu::AddressMode::Repeat,
+            address_mode_v: wgpu::AddressMode::Repeat,
+            address_mode_w: wgpu::AddressMode::Repeat,
+            mag_filter: filter(settings.mag_filter),
+            min_filter: filter(settings.min_filter),
+            mipmap_filter: filter(settings.mipmap_filter),
+            anisotropy_clamp: anisotropy,
+            ..Default::default()
+        })
+    }
+
+    fn uniform(settings: &SamplerSettings) -> SamplerSettingsUniform {
+        SamplerSettingsUniform {
+            lod_bias: settings.lod_bias,
+            _padding: [0.0; 3],
+        }
+    }
+}
@@ -2,17 +2,38 @@ use std::fs;
 
 use log::info;
 
+/// Bindings, lighting, shadows and the fragment shader shared by every shader
+/// that draws blocks.
+const BLOCK_SHADING_PATH: &str = "src/shaders/common.wgsl";
+
 /// Reads, and then compiles shader source code to create a ShaderModule.
 ///
 /// The ShaderModule can then be used to define programmable stages of a pipeline.
 pub fn create_shader(shader_path: &str, device: &wgpu::Device) -> wgpu::ShaderModule {
-    let shader_source = fs::read_to_string(shader_path)
-        .expect(format!("Failed to load shader at path: {}", shader_path).as_str());
+    compile(shader_path, read_source(shader_path), device)
+}
+
+/// Like `create_shader`, for shaders that draw blocks. Their source only has
+/// to provide a vertex shader, with the shared shading code put in front of it.
+pub fn create_block_shader(shader_path: &str, device: &wgpu::Device) -> wgpu::ShaderModule {
+    let source = format!(
+        "{}\n{}",
+        read_source(BLOCK_SHADING_PATH),
+        read_source(shader_path)
+    );
+    compile(shader_path, source, device)
+}
+
+fn read_source(shader_path: &str) -> String {
+    fs::read_to_string(shader_path)
+        .unwrap_or_else(|_| panic!("Failed to load shader at path: {}", shader_path))
+}
 
+fn compile(shader_path: &str, source: String, device: &wgpu::Device) -> wgpu::ShaderModule {
     info!("Loading shader from {}", shader_path);
     let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
         label: None,
-        source: wgpu::ShaderSource::Wgsl(shader_source.into()),
+        source: wgpu::ShaderSource::Wgsl(source.into()),
     });
     info!("Shader loaded from {}", shader_path);
     shader
@@ -0,0 +1,197 @@
+// Shading shared by every shader that draws blocks, put in front of each of
+// their sources by `shader::create_block_shader`. Each of them only has to
+// provide a `vs_main` filling in `VertexOutput`.
+
+struct CameraUniform {
+    view_proj: mat4x4<f32>,
+    view_position: vec4<f32>,
+};
+@group(1) @binding(0)
+var<uniform> camera: CameraUniform;
+
+struct VertexInput {
+    @location(0) vertex_position: vec3<f32>,
+    @location(1) tex_coords: vec2<f32>,
+    @location(2) texture_layer: u32,
+    @location(3) ambient_occlusion: f32,
+    @location(4) normal: vec3<f32>,
+    // Sky and block light levels baked into the mesh, from 0 to 1.
+    @location(5) voxel_light: vec2<f32>,
+    // xyz points along the texture's x axis, and w flips the bitangent.
+    @location(6) tangent: vec4<f32>,
+}
+
+struct VertexOutput {
+    // `@builtin(position)` tells wgpu that this value is used for clip coordinates
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) tex_coords: vec2<f32>,
+    @location(1) ambient_occlusion: f32,
+    @location(2) @interpolate(flat) texture_layer: u32,
+    @location(3) world_position: vec3<f32>,
+    @location(4) normal: vec3<f32>,
+    @location(5) voxel_light: vec2<f32>,
+    // Distance in front of the camera, for choosing a shadow cascade.
+    @location(6) view_depth: f32,
+    @location(7) tangent: vec4<f32>,
+    // Multiplies the texture's colour.
+    @location(8) tint: vec4<f32>,
+}
+
+@group(0) @binding(0)
+var t_diffuse: texture_2d_array<f32>;
+@group(0)@binding(1)
+var s_diffuse: sampler;
+
+struct SamplerSettings {
+    lod_bias: f32,
+}
+@group(0) @binding(2)
+var<uniform> sampler_settings: SamplerSettings;
+// Normal maps, in the same layers as the colour textures. Textures without
+// one get a flat normal map.
+@group(0) @binding(3)
+var t_normal: texture_2d_array<f32>;
+
+// Bends a surface normal by its normal map. The map's x axis points along the
+// tangent, y up the texture along the bitangent, and z out along the normal.
+fn mapped_normal(surface_normal: vec3<f32>, tangent: vec4<f32>, tex_coords: vec2<f32>, texture_layer: u32) -> vec3<f32> {
+    let normal = normalize(surface_normal);
+    // Interpolation can leave the tangent slightly off perpendicular.
+    let t = normalize(tangent.xyz - normal * dot(tangent.xyz, normal));
+    let bitangent = cross(normal, t) * tangent.w;
+    let sample = textureSampleBias(
+        t_normal,
+        s_diffuse,
+        tex_coords,
+        texture_layer,
+        sampler_settings.lod_bias,
+    ).xyz * 2.0 - 1.0;
+    return normalize(mat3x3<f32>(t, bitangent, normal) * sample);
+}
+
+struct Light {
+    // The direction the light travels in.
+    direction: vec3<f32>,
+    specular_strength: f32,
+    colour: vec3<f32>,
+    ambient: f32,
+    shininess: f32,
+}
+@group(2) @binding(0)
+var<uniform> light: Light;
+
+struct Shadows {
+    light_view_projections: array<mat4x4<f32>, 4>,
+    // View depth at which each cascade ends.
+    split_depths: vec4<f32>,
+    // Width of a shadow map texel in each cascade, in world units.
+    texel_world_sizes: vec4<f32>,
+    cascade_count: u32,
+    pcf_radius: u32,
+    texel_size: f32,
+}
+@group(3) @binding(0)
+var shadow_map: texture_depth_2d_array;
+@group(3) @binding(1)
+var shadow_sampler: sampler_comparison;
+@group(3) @binding(2)
+var<uniform> shadows: Shadows;
+
+// How much of the sun reaches a point, from 0 in full shadow to 1.
+//
+// Uses the first cascade reaching past the point, averaging comparisons over
+// a square of texels around it to soften the edges of shadows.
+fn sun_visibility(world_position: vec3<f32>, normal: vec3<f32>, view_depth: f32) -> f32 {
+    var cascade = 0u;
+    loop {
+        if cascade >= shadows.cascade_count {
+            // Past the last cascade, nothing is shadowed.
+            return 1.0;
+        }
+        if view_depth <= shadows.split_depths[cascade] {
+            break;
+        }
+        cascade += 1u;
+    }
+
+    // Nudging the point along its normal keeps surfaces from shadowing
+    // themselves. Larger cascades have larger texels, so need a larger nudge.
+    let offset = normal * shadows.texel_world_sizes[cascade];
+    let light_position = shadows.light_view_projections[cascade] * vec4<f32>(world_position + offset, 1.0);
+    let coords = light_position.xyz / light_position.w;
+    let uv = coords.xy * vec2<f32>(0.5, -0.5) + 0.5;
+    if any(uv < vec2<f32>(0.0)) || any(uv > vec2<f32>(1.0)) || coords.z > 1.0 {
+        return 1.0;
+    }
+
+    let radius = i32(shadows.pcf_radius);
+    var lit = 0.0;
+    for (var y = -radius; y <= radius; y += 1) {
+        for (var x = -radius; x <= radius; x += 1) {
+            let sample_uv = uv + vec2<f32>(f32(x), f32(y)) * shadows.texel_size;
+            lit += textureSampleCompareLevel(shadow_map, shadow_sampler, sample_uv, i32(cascade), coords.z);
+        }
+    }
+    let width = f32(radius * 2 + 1);
+    return lit / (width * width);
+}
+
+// How bright each light level is compared to the one above it.
+const LIGHT_FALLOFF: f32 = 0.8;
+// Colour of light given off by blocks like torches.
+const BLOCK_LIGHT_COLOUR: vec3<f32> = vec3<f32>(1.0, 0.85, 0.6);
+
+// Brightness of a voxel light level, where 1 is the full 15.
+fn light_brightness(level: f32) -> f32 {
+    if level <= 0.0 {
+        return 0.0;
+    }
+    return pow(LIGHT_FALLOFF, 15.0 * (1.0 - level));
+}
+
+// Ambient, diffuse and specular light reaching a point. Ambient occlusion only
+// darkens ambient light, since it approximates how enclosed the point is.
+//
+// Sunlight is scaled by how much sky light reaches the point, so caves stay
+// dark, and block light is added on top of it. Shadows only block direct
+// sunlight, leaving ambient light alone, and are looked up with the geometric
+// normal rather than the normal mapped one.
+fn blinn_phong(surface_normal: vec3<f32>, geometric_normal: vec3<f32>, world_position: vec3<f32>, ambient_occlusion: f32, voxel_light: vec2<f32>, view_depth: f32) -> vec3<f32> {
+    let normal = normalize(surface_normal);
+    let to_light = -light.direction;
+    let to_camera = normalize(camera.view_position.xyz - world_position);
+    let half_way = normalize(to_light + to_camera);
+
+    let ambient = light.ambient * ambient_occlusion;
+    let diffuse = max(dot(normal, to_light), 0.0);
+    var specular = 0.0;
+    if diffuse > 0.0 {
+        specular = pow(max(dot(normal, half_way), 0.0), light.shininess) * light.specular_strength;
+    }
+
+    let sun = sun_visibility(world_position, normalize(geometric_normal), view_depth);
+    let sky = light_brightness(voxel_light.x);
+    let block = light_brightness(voxel_light.y) * ambient_occlusion;
+    return light.colour * (ambient + (diffuse + specular) * sun) * sky + BLOCK_LIGHT_COLOUR * block;
+}
+
+@fragment
+fn fs_main(input: VertexOutput) -> @location(0) vec4<f32> {
+    let colour = textureSampleBias(
+        t_diffuse,
+        s_diffuse,
+        input.tex_coords,
+        input.texture_layer,
+        sampler_settings.lod_bias,
+    ) * input.tint;
+    let normal = mapped_normal(input.normal, input.tangent, input.tex_coords, input.texture_layer);
+    let lighting = blinn_phong(
+        normal,
+        input.normal,
+        input.world_position,
+        input.ambient_occlusion,
+        input.voxel_light,
+        input.view_depth,
+    );
+    return vec4<f32>(colour.rgb * lighting, colour.a);
+}
@@ -0,0 +1,41 @@
+// Draws a mesh once per instance, each with its own transform, tint and
+// texture. Shading is in `common.wgsl`.
+
+// Per-instance data, stepped once per instance rather than once per vertex.
+struct InstanceInput {
+    @location(8) model_0: vec4<f32>,
+    @location(9) model_1: vec4<f32>,
+    @location(10) model_2: vec4<f32>,
+    @location(11) model_3: vec4<f32>,
+    @location(12) tint: vec4<f32>,
+    @location(13) texture_layer: u32,
+}
+
+@vertex
+fn vs_main(model: VertexInput, instance: InstanceInput) -> VertexOutput {
+    let model_matrix = mat4x4<f32>(
+        instance.model_0,
+        instance.model_1,
+        instance.model_2,
+        instance.model_3,
+    );
+
+    var out: VertexOutput;
+    out.tex_coords = model.tex_coords;
+    out.ambient_occlusion = model.ambient_occlusion;
+    out.tint = instance.tint;
+    // The instance chooses the texture, so one mesh can be drawn with many.
+    out.texture_layer = instance.texture_layer;
+    let world_position = model_matrix * vec4<f32>(model.vertex_position, 1.0);
+    out.world_position = world_position.xyz;
+    // Instances are only rotated and scaled evenly, so the model matrix can
+    // transform normals too.
+    out.normal = normalize((model_matrix * vec4<f32>(model.normal, 0.0)).xyz);
+    out.voxel_light = model.voxel_light;
+    out.tangent = vec4<f32>(normalize((model_matrix * vec4<f32>(model.tangent.xyz, 0.0)).xyz), model.tangent.w);
+    out.clip_position = camera.view_proj * world_position;
+    // A perspective projection leaves the view depth in w.
+    out.view_depth = out.clip_position.w;
+
+    return out;
+}
@@ -1,19 +1,5 @@
-struct CameraUniform {
-    view_proj: mat4x4<f32>,
-};
-@group(1) @binding(0)
-var<uniform> camera: CameraUniform;
-
-struct VertexInput {
-    @location(0) vertex_position: vec3<f32>,
-    @location(1) tex_coords: vec2<f32>,
-}
-
-struct VertexOutput {
-    // `@builtin(position)` tells wgpu that this value is used for clip coordinates
-    @builtin(position) clip_position: vec4<f32>,
-    @location(0) tex_coords: vec2<f32>,
-}
+// Draws chunk meshes, which are already in world space. Shading is in
+// `common.wgsl`.
 
 @vertex
 fn vs_main(model: VertexInput) -> VertexOutput {
@@ -21,18 +7,17 @@ fn vs_main(model: VertexInput) -> VertexOutput {
     
     // Passthrough color
     out.tex_coords = model.tex_coords;
+    out.ambient_occlusion = model.ambient_occlusion;
+    out.texture_layer = model.texture_layer;
+    out.world_position = model.vertex_position;
+    out.normal = model.normal;
+    out.voxel_light = model.voxel_light;
+    out.tangent = model.tangent;
+    out.tint = vec4<f32>(1.0);
     // Clip position is the position in screenspace, e.g. the pixel the vertex is on.
     out.clip_position = camera.view_proj * vec4<f32>(model.vertex_position, 1.0);
+    // A perspective projection leaves the view depth in w.
+    out.view_depth = out.clip_position.w;
 
     return out;
 }
-
-@group(0) @binding(0)
-var t_diffuse: texture_2d<f32>;
-@group(0)@binding(1)
-var s_diffuse: sampler;
-
-@fragment
-fn fs_main(input: VertexOutput) -> @location(0) vec4<f32> {
-    return textureSample(t_diffuse, s_diffuse, input.tex_coords);
-}
\ No newline at end of file
@@ -0,0 +1,14 @@
+// Renders chunk meshes into a shadow map cascade. Only depth is needed, so
+// there's no fragment shader.
+
+@group(0) @binding(0)
+var<uniform> light_view_projection: mat4x4<f32>;
+
+struct VertexInput {
+    @location(0) vertex_position: vec3<f32>,
+}
+
+@vertex
+fn vs_main(model: VertexInput) -> @builtin(position) vec4<f32> {
+    return light_view_projection * vec4<f32>(model.vertex_position, 1.0);
+}
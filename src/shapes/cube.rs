@@ -1,6 +1,10 @@
 use wgpu::{util::DeviceExt, Buffer, Device};
 
-use crate::primitives::vertex::Vertex;
+use crate::{
+    block::face::Face,
+    meshing::chunk_mesh::{face_axes, face_tangent},
+    primitives::vertex::Vertex,
+};
 
 pub struct Cube {
     pub vertex_buffer: Buffer,
@@ -10,13 +14,13 @@ pub struct Cube {
 impl Cube {
     pub fn new(device: &Device) -> Self {
         let vertex_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-            label: Some("Triangle Vertex Buffer"),
+            label: Some("Cube Vertex Buffer"),
             contents: bytemuck::cast_slice(&Cube::get_vertices()),
             usage: wgpu::BufferUsages::VERTEX,
         });
 
         let index_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-            label: Some("Triangle Index Buffer"),
+            label: Some("Cube Index Buffer"),
             contents: bytemuck::cast_slice(&Cube::get_indices()),
             usage: wgpu::BufferUsages::INDEX,
         });
@@ -27,49 +31,48 @@ impl Cube {
         }
     }
 
+    /// Four vertices per face, so that each face has its own normal.
     // 0,0______1,0
     //  |        |
     //  |        |
     //  |        |
     //  |________|
     // 0,1      1,1
-    pub fn get_vertices() -> [Vertex; 16] {
-        [
-            Vertex::new((-1.0, -1.0, 1.0), (0.0, 1.0)), // 0 - Front Bottom Left
-            Vertex::new((-1.0, 1.0, 1.0), (0.0, 0.0)), // 1 - Front Top Left
-            Vertex::new((1.0, 1.0, 1.0), (1.0, 0.0)), // 2 - Front Top Right
-            Vertex::new((1.0, -1.0, 1.0), (1.0, 1.0)), // 3 - Front Bottom Right
-
-            Vertex::new((-1.0, -1.0, -1.0), (1.0, 1.0)), // 4 - Back Bottom Left
-            Vertex::new((-1.0, 1.0, -1.0), (1.0, 0.0)), // 5 - Back Top Left
-            Vertex::new((1.0, 1.0, -1.0), (0.0, 0.0)), // 6 - Back Top Right
-            Vertex::new((1.0, -1.0, -1.0), (0.0, 1.0)), // 7 - Back Bottom Right
-
-            Vertex::new((-1.0, 1.0, 1.0), (0.0, 0.0)),   // 8 - Top Front Left (new)
-            Vertex::new((1.0, 1.0, 1.0), (1.0, 0.0)),     // 9 - Top Front Right (new)
-            Vertex::new((-1.0, 1.0, -1.0), (0.0, 1.0)),  // 10 - Top Back Left (new)
-            Vertex::new((1.0, 1.0, -1.0), (1.0, 1.0)),   // 11 - Top Back Right (new)
-
-            Vertex::new((-1.0, -1.0, 1.0), (0.0, 0.0)),   // 12 - Bottom Front Left (new)
-            Vertex::new((1.0, -1.0, 1.0), (1.0, 0.0)),     // 13 - Bottom Front Right (new)
-            Vertex::new((-1.0, -1.0, -1.0), (0.0, 1.0)),  // 14 - Bottom Back Left (new)
-            Vertex::new((1.0, -1.0, -1.0), (1.0, 1.0)),   // 15 - Bottom Back Right (new)
-        ]
+    pub fn get_vertices() -> [Vertex; 24] {
+        let mut vertices = [Vertex::new((0.0, 0.0, 0.0), (0.0, 0.0), (0.0, 0.0, 0.0)); 24];
+        for (face, corners) in Face::ALL.into_iter().zip(vertices.chunks_mut(4)) {
+            // Wound from the bottom left, clockwise as seen from outside,
+            // like chunk mesh quads.
+            let (u, v) = face_axes(face);
+            let normal = face.normal();
+            let positions = [
+                normal - u - v,
+                normal - u + v,
+                normal + u + v,
+                normal + u - v,
+            ];
+            let tex_coords = [(0.0, 1.0), (0.0, 0.0), (1.0, 0.0), (1.0, 1.0)];
+            for ((corner, position), tex) in corners.iter_mut().zip(positions).zip(tex_coords) {
+                *corner = Vertex::new(
+                    (position.x, position.y, position.z),
+                    tex,
+                    (normal.x, normal.y, normal.z),
+                );
+                corner.tangent = face_tangent(face);
+            }
+        }
+        vertices
     }
 
     pub fn get_indices() -> [u16; 36] {
-        [
-            0, 1, 2, 0, 2, 3, // front face (Z-positive)
-            7, 6, 5, 7, 5, 4, // back face (Z-negative)
-            3, 2, 6, 3, 6, 7, // right face (X-positive)
-            4, 5, 1, 4, 1, 0, // left face (X-negative)
-            8, 10, 11, 8, 11, 9, // top face (Y-positive)
-            15, 14, 12, 13, 15, 12, // bottom face (Y-negative)
-        ]
-    }
-
-    pub fn get_vertices_len() -> u32 {
-        16
+        let mut indices = [0; 36];
+        for (face, face_indices) in indices.chunks_mut(6).enumerate() {
+            let first = face as u16 * 4;
+            for (index, corner) in face_indices.iter_mut().zip([0, 1, 2, 0, 2, 3]) {
+                *index = first + corner;
+            }
+        }
+        indices
     }
 
     pub fn get_indices_len() -> u32 {
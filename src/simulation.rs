@@ -0,0 +1,103 @@
+use log::info;
+use ultraviolet as uv;
+
+use crate::{
+    camera::{
+        camera::Camera, camera_controller::CameraController,
+        first_person_controller::FirstPersonController, orbit_controller::OrbitController,
+    },
+    input::{action::Action, tick_input::TickInput},
+};
+
+/// Where the camera starts out, and what it's looking at.
+const SPAWN_EYE: uv::Vec3 = uv::Vec3::new(0.0, 110.0, 60.0);
+const SPAWN_TARGET: uv::Vec3 = uv::Vec3::new(0.0, 70.0, 0.0);
+
+/// Speed of each camera controller, in blocks per second.
+const ORBIT_SPEED: f32 = 12.0;
+const FLY_SPEED: f32 = 20.0;
+/// How far ahead of the camera the orbit camera's target is put when
+/// switching to it.
+const ORBIT_DISTANCE: f32 = 60.0;
+/// Radians the first person camera turns per unit of mouse movement.
+const MOUSE_SENSITIVITY: f32 = 0.003;
+
+/// Everything that moves on in fixed steps, which for now is the player's
+/// camera.
+///
+/// Each step depends only on its input and the length of a step, so the
+/// same inputs always end up in the same state, with or without a window.
+pub struct Simulation {
+    camera: Camera,
+    /// The camera as it was before the last step, for drawing frames that
+    /// fall between steps.
+    previous_camera: Camera,
+    camera_controller: Box<dyn CameraController>,
+    first_person: bool,
+    tick: u64,
+}
+
+impl Simulation {
+    pub fn new(aspect: f32) -> Self {
+        let camera = Camera::new(SPAWN_EYE, SPAWN_TARGET, aspect);
+        Self {
+            previous_camera: camera.clone(),
+            camera,
+            camera_controller: Box::new(OrbitController::new(ORBIT_SPEED)),
+            first_person: false,
+            tick: 0,
+        }
+    }
+
+    pub fn camera(&self) -> &Camera {
+        &self.camera
+    }
+
+    /// The camera part way between the last two steps, where an alpha of 0
+    /// is the one before last.
+    pub fn interpolated_camera(&self, alpha: f32) -> Camera {
+        self.camera.interpolate(&self.previous_camera, alpha)
+    }
+
+    pub fn first_person(&self) -> bool {
+        self.first_person
+    }
+
+    /// Steps run so far.
+    pub fn tick(&self) -> u64 {
+        self.tick
+    }
+
+    pub fn step(&mut self, input: &TickInput, delta_time: f32) {
+        self.previous_camera = self.camera.clone();
+        if input.action_just_pressed(Action::SwitchCamera) {
+            self.switch_camera_controller();
+            // Cut straight to the new camera rather than sliding over.
+            self.previous_camera = self.camera.clone();
+        }
+        self.camera_controller
+            .update_camera(&mut self.camera, input, delta_time);
+        self.tick += 1;
+    }
+
+    /// Switches between orbiting the target and flying around in first
+    /// person.
+    fn switch_camera_controller(&mut self) {
+        self.first_person = !self.first_person;
+        let camera = &mut self.camera;
+        if !self.first_person {
+            info!("Switched to the orbit camera");
+            // The first person camera keeps its target right in front of it.
+            let direction = (camera.target - camera.eye).normalized();
+            camera.target = camera.eye + direction * ORBIT_DISTANCE;
+            self.camera_controller = Box::new(OrbitController::new(ORBIT_SPEED));
+        } else {
+            info!("Switched to the first person camera");
+            self.camera_controller = Box::new(FirstPersonController::new(
+                camera,
+                FLY_SPEED,
+                MOUSE_SENSITIVITY,
+            ));
+        }
+    }
+}
@@ -0,0 +1,580 @@
+use std::{collections::HashMap, fmt, path::Path};
+
+use image::RgbaImage;
+use log::info;
+use wgpu::{Device, Queue, TextureDescriptor};
+
+use crate::{block::registry::BlockRegistry, sampler::Sampler};
+
+/// Normal pointing straight out of a surface, encoded as a pixel. Tiles
+/// without a normal map use this.
+pub const FLAT_NORMAL: image::Rgba<u8> = image::Rgba([128, 128, 255, 255]);
+
+/// What a texture array's tiles hold, which decides how they're stored and
+/// mipmapped.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum TileKind {
+    /// sRGB colours.
+    #[default]
+    Colour,
+    /// Tangent-space normal maps, with each axis mapped from -1..1 to 0..255.
+    Normal,
+}
+
+/// Collects equally sized tiles, such as block face textures, to be packed
+/// into one `D2Array` texture with a layer per tile.
+///
+/// An array is used rather than an atlas so that tiles can be repeated across
+/// merged faces without bleeding into their neighbours. That goes for mipmaps
+/// too: each tile's mip chain is built from that tile alone.
+#[derive(Default)]
+pub struct TextureArrayBuilder {
+    kind: TileKind,
+    tile_size: Option<(u32, u32)>,
+    tiles: Vec<RgbaImage>,
+    layers: HashMap<String, u32>,
+}
+
+impl TextureArrayBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn normal_maps() -> Self {
+        Self {
+            kind: TileKind::Normal,
+            ..Self::default()
+        }
+    }
+
+    /// Normal maps for every tile in `colours`, in the same layers, loaded
+    /// from `<texture_dir>/<name>_n.png`.
+    ///
+    /// Tiles without a normal map get a flat one, so they can be drawn the
+    /// same way as tiles with one.
+    pub fn normal_maps_for(
+        colours: &TextureArrayBuilder,
+        texture_dir: &str,
+    ) -> Result<Self, TextureArrayError> {
+        let mut builder = Self::normal_maps();
+        let Some((width, height)) = colours.tile_size else {
+            return Ok(builder);
+        };
+        builder.tile_size = colours.tile_size;
+
+        let mut names: Vec<(&String, &u32)> = colours.layers.iter().collect();
+        names.sort_by_key(|(_, &layer)| layer);
+        for (name, _) in names {
+            let path = Path::new(texture_dir).join(format!("{}_n.png", name));
+            if path.is_file() {
+                builder.add_file(name, &path.to_string_lossy())?;
+            } else {
+                let flat = RgbaImage::from_pixel(width, height, FLAT_NORMAL);
+                builder.add_image(name, flat, "flat normal map")?;
+            }
+        }
+        Ok(builder)
+    }
+
+    /// Adds every texture used by a block, loaded from
+    /// `<texture_dir>/<name>.png`.
+    pub fn from_registry(
+        registry: &BlockRegistry,
+        texture_dir: &str,
+    ) -> Result<Self, TextureArrayError> {
+        let mut builder = Self::new();
+        for name in registry.texture_names() {
+            let path = Path::new(texture_dir).join(format!("{}.png", name));
+            builder.add_file(name, &path.to_string_lossy())?;
+        }
+        Ok(builder)
+    }
+
+    /// Loads an image and adds it as a tile, returning its layer.
+    pub fn add_file(&mut self, name: &str, path: &str) -> Result<u32, TextureArrayError> {
+        let image = image::open(path)
+            .map_err(|error| TextureArrayError::Image {
+                path: path.to_string(),
+                error,
+            })?
+            .to_rgba8();
+        self.add_image(name, image, path)
+    }
+
+    /// Adds a tile, returning its layer. `source` names where the image came
+    /// from in errors.
+    pub fn add_image(
+        &mut self,
+        name: &str,
+        image: RgbaImage,
+        source: &str,
+    ) -> Result<u32, TextureArrayError> {
+        if self.layers.contains_key(name) {
+            return Err(TextureArrayError::DuplicateName {
+                name: name.to_string(),
+            });
+        }
+
+        // The first tile decides the size of every tile.
+        let size = image.dimensions();
+        let expected = *self.tile_size.get_or_insert(size);
+        if size != expected {
+            return Err(TextureArrayError::SizeMismatch {
+                path: source.to_string(),
+                expected,
+                found: size,
+            });
+        }
+
+        let layer = self.tiles.len() as u32;
+        self.tiles.push(image);
+        self.layers.insert(name.to_string(), layer);
+        Ok(layer)
+    }
+
+    pub fn tile_size(&self) -> Option<(u32, u32)> {
+        self.tile_size
+    }
+
+    pub fn layer(&self, name: &str) -> Option<u32> {
+        self.layers.get(name).copied()
+    }
+
+    pub fn tiles(&self) -> &[RgbaImage] {
+        &self.tiles
+    }
+
+    /// Uploads the tiles to the GPU.
+    pub fn build(self, device: &Device, queue: &Queue) -> Result<TextureArray, TextureArrayError> {
+        let Some((width, height)) = self.tile_size else {
+            return Err(TextureArrayError::Empty);
+        };
+
+        let size = wgpu::Extent3d {
+            width,
+            height,
+            depth_or_array_layers: self.tiles.len() as u32,
+        };
+        let mip_level_count = size.max_mips(wgpu::TextureDimension::D2);
+        let (label, format) = match self.kind {
+            TileKind::Colour => ("Block Texture Array", wgpu::TextureFormat::Rgba8UnormSrgb),
+            // Normals aren't colours, so mustn't be converted from sRGB.
+            TileKind::Normal => ("Block Normal Map Array", wgpu::TextureFormat::Rgba8Unorm),
+        };
+        let inner_texture = device.create_texture(&TextureDescriptor {
+            label: Some(label),
+            size,
+            mip_level_count,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+            view_formats: &[],
+        });
+
+        let mip_chains: Vec<Vec<RgbaImage>> = self
+            .tiles
+            .iter()
+            .map(|tile| generate_mip_chain(tile, mip_level_count, self.kind))
+            .collect();
+        for mip_level in 0..mip_level_count {
+            let mip_size = size.mip_level_size(mip_level, wgpu::TextureDimension::D2);
+            let data: Vec<u8> = mip_chains
+                .iter()
+                .flat_map(|chain| chain[mip_level as usize].as_raw().iter().copied())
+                .collect();
+            queue.write_texture(
+                wgpu::ImageCopyTexture {
+                    texture: &inner_texture,
+                    mip_level,
+                    origin: wgpu::Origin3d::ZERO,
+                    aspect: wgpu::TextureAspect::All,
+                },
+                &data,
+                wgpu::ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: Some(4 * mip_size.width),
+                    rows_per_image: Some(mip_size.height),
+                },
+                mip_size,
+            );
+        }
+
+        let view = inner_texture.create_view(&wgpu::TextureViewDescriptor {
+            label: Some(label),
+            dimension: Some(wgpu::TextureViewDimension::D2Array),
+            ..Default::default()
+        });
+        info!(
+            "Built {} of {} {}x{} tiles with {} mip levels",
+            label,
+            self.tiles.len(),
+            width,
+            height,
+            mip_level_count
+        );
+
+        Ok(TextureArray {
+            inner_texture,
+            view,
+            tile_size: (width, height),
+            mip_level_count,
+            layers: self.layers,
+        })
+    }
+}
+
+/// Halves a tile until it reaches `mip_level_count` levels, the first being
+/// the tile itself.
+pub fn generate_mip_chain(
+    tile: &RgbaImage,
+    mip_level_count: u32,
+    kind: TileKind,
+) -> Vec<RgbaImage> {
+    let mut chain = vec![tile.clone()];
+    while chain.len() < mip_level_count as usize {
+        let previous = chain.last().unwrap();
+        let next = match kind {
+            TileKind::Colour => downsample(previous),
+            TileKind::Normal => downsample_normals(previous),
+        };
+        chain.push(next);
+    }
+    chain
+}
+
+/// Averages each 2x2 block of pixels into one.
+///
+/// Colours are averaged in linear space, since the texture is sRGB, and
+/// weighted by alpha so that fully transparent pixels (e.g. the gaps in
+/// leaves) don't darken the edges around them.
+fn downsample(image: &RgbaImage) -> RgbaImage {
+    let (width, height) = image.dimensions();
+    RgbaImage::from_fn((width / 2).max(1), (height / 2).max(1), |x, y| {
+        let mut colour = [0.0; 3];
+        let mut alpha = 0.0;
+        for (dx, dy) in [(0, 0), (1, 0), (0, 1), (1, 1)] {
+            let pixel = image.get_pixel((x * 2 + dx).min(width - 1), (y * 2 + dy).min(height - 1));
+            let pixel_alpha = pixel[3] as f32 / 255.0;
+            for (channel, sum) in colour.iter_mut().enumerate() {
+                *sum += srgb_to_linear(pixel[channel]) * pixel_alpha;
+            }
+            alpha += pixel_alpha;
+        }
+
+        let colour = if alpha > 0.0 {
+            colour.map(|sum| linear_to_srgb(sum / alpha))
+        } else {
+            [0; 3]
+        };
+        image::Rgba([
+            colour[0],
+            colour[1],
+            colour[2],
+            (alpha / 4.0 * 255.0).round() as u8,
+        ])
+    })
+}
+
+/// Averages each 2x2 block of normals into one, scaled back to unit length.
+///
+/// Normals that cancel out, like on either side of a ridge, average to
+/// pointing straight out of the surface.
+fn downsample_normals(image: &RgbaImage) -> RgbaImage {
+    let (width, height) = image.dimensions();
+    RgbaImage::from_fn((width / 2).max(1), (height / 2).max(1), |x, y| {
+        let mut normal = [0.0; 3];
+        let mut alpha = 0.0;
+        for (dx, dy) in [(0, 0), (1, 0), (0, 1), (1, 1)] {
+            let pixel = image.get_pixel((x * 2 + dx).min(width - 1), (y * 2 + dy).min(height - 1));
+            for (channel, sum) in normal.iter_mut().enumerate() {
+                *sum += pixel[channel] as f32 / 255.0 * 2.0 - 1.0;
+            }
+            alpha += pixel[3] as f32;
+        }
+
+        let length = normal.iter().map(|n| n * n).sum::<f32>().sqrt();
+        if length < f32::EPSILON {
+            return FLAT_NORMAL;
+        }
+        let [x, y, z] = normal.map(|n| ((n / length + 1.0) / 2.0 * 255.0).round() as u8);
+        image::Rgba([x, y, z, (alpha / 4.0).round() as u8])
+    })
+}
+
+fn srgb_to_linear(value: u8) -> f32 {
+    let value = value as f32 / 255.0;
+    if value <= 0.04045 {
+        value / 12.92
+    } else {
+        ((value + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+fn linear_to_srgb(value: f32) -> u8 {
+    let value = if value <= 0.0031308 {
+        value * 12.92
+    } else {
+        1.055 * value.powf(1.0 / 2.4) - 0.055
+    };
+    (value.clamp(0.0, 1.0) * 255.0).round() as u8
+}
+
+/// Tiles uploaded as a `D2Array` texture, looked up by name.
+pub struct TextureArray {
+    pub inner_texture: wgpu::Texture,
+    pub view: wgpu::TextureView,
+    pub tile_size: (u32, u32),
+    pub mip_level_count: u32,
+    layers: HashMap<String, u32>,
+}
+
+impl TextureArray {
+    pub fn layer(&self, name: &str) -> Option<u32> {
+        self.layers.get(name).copied()
+    }
+
+    pub fn layer_count(&self) -> u32 {
+        self.layers.len() as u32
+    }
+
+    pub fn get_bind_group_layout(device: &Device) -> wgpu::BindGroupLayout {
+        device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Texture Array Bind Group Layout"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        multisampled: false,
+                        view_dimension: wgpu::TextureViewDimension::D2Array,
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                // Normal maps, in the same layers as the colour textures.
+                wgpu::BindGroupLayoutEntry {
+                    binding: 3,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        multisampled: false,
+                        view_dimension: wgpu::TextureViewDimension::D2Array,
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                    },
+                    count: None,
+                },
+            ],
+        })
+    }
+
+    /// Binds the array with its normal maps, which have to be laid out in
+    /// the same layers.
+    pub fn create_bind_group(
+        &self,
+        device: &Device,
+        layout: &wgpu::BindGroupLayout,
+        sampler: &Sampler,
+        normal_maps: &TextureArray,
+    ) -> wgpu::BindGroup {
+        device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Texture Array Bind Group"),
+            layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&self.view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(&sampler.sampler),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: sampler.settings_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 3,
+                    resource: wgpu::BindingResource::TextureView(&normal_maps.view),
+                },
+            ],
+        })
+    }
+}
+
+#[derive(Debug)]
+pub enum TextureArrayError {
+    Image {
+        path: String,
+        error: image::ImageError,
+    },
+    SizeMismatch {
+        path: String,
+        expected: (u32, u32),
+        found: (u32, u32),
+    },
+    DuplicateName {
+        name: String,
+    },
+    Empty,
+}
+
+impl fmt::Display for TextureArrayError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TextureArrayError::Image { path, error } => {
+                write!(f, "failed to load texture at {}: {}", path, error)
+            }
+            TextureArrayError::SizeMismatch {
+                path,
+                expected,
+                found,
+            } => write!(
+                f,
+                "texture at {} is {}x{}, but every texture has to be {}x{}",
+                path, found.0, found.1, expected.0, expected.1
+            ),
+            TextureArrayError::DuplicateName { name } => {
+                write!(f, "texture \"{}\" is added more than once", name)
+            }
+            TextureArrayError::Empty => write!(f, "texture array has no textures"),
+        }
+    }
+}
+
+impl std::error::Error for TextureArrayError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            TextureArrayError::Image { error, .. } => Some(error),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TILE_SIZE: u32 = 16;
+
+    fn tile(pixel: image::Rgba<u8>) -> RgbaImage {
+        RgbaImage::from_pixel(TILE_SIZE, TILE_SIZE, pixel)
+    }
+
+    #[test]
+    fn tiles_of_another_size_are_rejected() {
+        let mut builder = TextureArrayBuilder::new();
+        let pixel = image::Rgba([255; 4]);
+        assert_eq!(
+            builder
+                .add_image("stone", tile(pixel), "stone.png")
+                .unwrap(),
+            0
+        );
+
+        let small = RgbaImage::from_pixel(8, 8, pixel);
+        let result = builder.add_image("dirt", small, "dirt.png");
+        assert!(matches!(
+            result,
+            Err(TextureArrayError::SizeMismatch {
+                path,
+                expected: (TILE_SIZE, TILE_SIZE),
+                found: (8, 8),
+            }) if path == "dirt.png"
+        ));
+        assert_eq!(builder.layer("dirt"), None);
+        assert_eq!(builder.tiles().len(), 1);
+    }
+
+    #[test]
+    fn names_can_only_be_added_once() {
+        let mut builder = TextureArrayBuilder::new();
+        let pixel = image::Rgba([255; 4]);
+        builder
+            .add_image("stone", tile(pixel), "stone.png")
+            .unwrap();
+
+        let result = builder.add_image("stone", tile(pixel), "other/stone.png");
+        assert!(matches!(
+            result,
+            Err(TextureArrayError::DuplicateName { name }) if name == "stone"
+        ));
+        assert_eq!(builder.tiles().len(), 1);
+    }
+
+    #[test]
+    fn mip_chains_halve_down_to_a_single_pixel() {
+        let size = wgpu::Extent3d {
+            width: TILE_SIZE,
+            height: TILE_SIZE,
+            depth_or_array_layers: 1,
+        };
+        let mip_level_count = size.max_mips(wgpu::TextureDimension::D2);
+        assert_eq!(mip_level_count, TILE_SIZE.ilog2() + 1);
+
+        let chain = generate_mip_chain(
+            &tile(image::Rgba([255; 4])),
+            mip_level_count,
+            TileKind::Colour,
+        );
+        assert_eq!(chain.len(), mip_level_count as usize);
+        for (level, image) in chain.iter().enumerate() {
+            let expected = TILE_SIZE >> level;
+            assert_eq!(image.dimensions(), (expected, expected));
+        }
+    }
+
+    #[test]
+    fn transparent_pixels_dont_darken_the_average() {
+        // Every other pixel is a fully transparent black gap.
+        let colour = image::Rgba([200, 100, 50, 255]);
+        let image = RgbaImage::from_fn(TILE_SIZE, TILE_SIZE, |x, y| {
+            if (x + y) % 2 == 0 {
+                colour
+            } else {
+                image::Rgba([0; 4])
+            }
+        });
+
+        let downsampled = downsample(&image);
+        for pixel in downsampled.pixels() {
+            assert_eq!(*pixel, image::Rgba([200, 100, 50, 128]));
+        }
+    }
+
+    #[test]
+    fn averaged_normals_are_unit_length() {
+        // Normals tilted to the right beside flat ones, which average to a
+        // normal shorter than unit length.
+        let image = RgbaImage::from_fn(TILE_SIZE, TILE_SIZE, |x, _| {
+            if x % 2 == 0 {
+                image::Rgba([218, 128, 218, 255])
+            } else {
+                FLAT_NORMAL
+            }
+        });
+
+        let downsampled = downsample_normals(&image);
+        for pixel in downsampled.pixels() {
+            let normal = [0, 1, 2].map(|channel| pixel[channel] as f32 / 255.0 * 2.0 - 1.0);
+            let length = normal.iter().map(|n| n * n).sum::<f32>().sqrt();
+            assert!((length - 1.0).abs() < 0.01, "{:?} isn't unit length", pixel);
+            assert!(normal[0] > 0.0);
+        }
+    }
+}
@@ -1,3 +1,4 @@
+use log::warn;
 use winit::{
     dpi::{LogicalSize, PhysicalSize},
     event_loop::EventLoop,
@@ -12,6 +13,26 @@ pub fn toggle_fullscreen(window: &Window) {
     }
 }
 
+/// Grabs and hides the cursor so mouse movement can turn the camera, or
+/// releases it again.
+pub fn set_cursor_grabbed(window: &Window, grabbed: bool) {
+    use winit::window::CursorGrabMode;
+
+    let result = if grabbed {
+        // Some platforms can only confine the cursor to the window, and
+        // others can only lock it in place.
+        window
+            .set_cursor_grab(CursorGrabMode::Confined)
+            .or_else(|_| window.set_cursor_grab(CursorGrabMode::Locked))
+    } else {
+        window.set_cursor_grab(CursorGrabMode::None)
+    };
+    if let Err(error) = result {
+        warn!("Failed to change cursor grab: {}", error);
+    }
+    window.set_cursor_visible(!grabbed);
+}
+
 pub fn get_window_sizes(
     event_loop: &EventLoop<()>,
     window_size: [u32; 2],
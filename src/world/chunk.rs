@@ -0,0 +1,190 @@
+use ultraviolet as uv;
+
+use super::lighting::LightChannel;
+
+/// Numeric ID of a block type. ID 0 is always air.
+pub type BlockId = u16;
+
+pub const AIR: BlockId = 0;
+
+/// Numeric ID of a biome, indexing into `worldgen::biome::Biomes`.
+pub type BiomeId = u8;
+
+/// Chunks are columns spanning the full height of the world.
+pub const CHUNK_WIDTH: usize = 16;
+pub const CHUNK_HEIGHT: usize = 256;
+pub const CHUNK_VOLUME: usize = CHUNK_WIDTH * CHUNK_HEIGHT * CHUNK_WIDTH;
+
+/// Integer coordinates of a chunk column, in chunks rather than blocks.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ChunkPos {
+    pub x: i32,
+    pub z: i32,
+}
+
+impl ChunkPos {
+    pub fn new(x: i32, z: i32) -> Self {
+        Self { x, z }
+    }
+
+    /// Returns the chunk containing the given world block position.
+    pub fn from_block_pos(pos: uv::IVec3) -> Self {
+        // div_euclid rounds towards negative infinity, so block -1 lands in
+        // chunk -1 rather than chunk 0.
+        Self {
+            x: pos.x.div_euclid(CHUNK_WIDTH as i32),
+            z: pos.z.div_euclid(CHUNK_WIDTH as i32),
+        }
+    }
+
+    /// World position of the chunk's (0, 0, 0) block.
+    pub fn origin(&self) -> uv::IVec3 {
+        uv::IVec3::new(self.x * CHUNK_WIDTH as i32, 0, self.z * CHUNK_WIDTH as i32)
+    }
+
+    pub fn offset(&self, dx: i32, dz: i32) -> Self {
+        Self::new(self.x + dx, self.z + dz)
+    }
+}
+
+/// Converts a world block position into a position local to its chunk.
+///
+/// Returns None if the position is above or below the world.
+pub fn local_block_pos(pos: uv::IVec3) -> Option<(usize, usize, usize)> {
+    if pos.y < 0 || pos.y >= CHUNK_HEIGHT as i32 {
+        return None;
+    }
+
+    Some((
+        pos.x.rem_euclid(CHUNK_WIDTH as i32) as usize,
+        pos.y as usize,
+        pos.z.rem_euclid(CHUNK_WIDTH as i32) as usize,
+    ))
+}
+
+#[derive(Clone, PartialEq, Eq, Hash)]
+pub struct Chunk {
+    blocks: Box<[BlockId]>,
+    // One biome per column, laid out x first then z.
+    biomes: Box<[BiomeId]>,
+    // Sky light in the high four bits of each entry, block light in the low
+    // four. Laid out like blocks.
+    light: Box<[u8]>,
+}
+
+impl Chunk {
+    pub fn new() -> Self {
+        Self::filled(AIR)
+    }
+
+    pub fn filled(block: BlockId) -> Self {
+        Self {
+            blocks: vec![block; CHUNK_VOLUME].into_boxed_slice(),
+            biomes: vec![0; CHUNK_WIDTH * CHUNK_WIDTH].into_boxed_slice(),
+            light: vec![0; CHUNK_VOLUME].into_boxed_slice(),
+        }
+    }
+
+    pub fn get_block(&self, x: usize, y: usize, z: usize) -> BlockId {
+        self.blocks[Self::index(x, y, z)]
+    }
+
+    pub fn set_block(&mut self, x: usize, y: usize, z: usize, block: BlockId) {
+        self.blocks[Self::index(x, y, z)] = block;
+    }
+
+    pub fn get_biome(&self, x: usize, z: usize) -> BiomeId {
+        self.biomes[z * CHUNK_WIDTH + x]
+    }
+
+    pub fn set_biome(&mut self, x: usize, z: usize, biome: BiomeId) {
+        self.biomes[z * CHUNK_WIDTH + x] = biome;
+    }
+
+    pub fn get_light(&self, x: usize, y: usize, z: usize, channel: LightChannel) -> u8 {
+        let light = self.light[Self::index(x, y, z)];
+        match channel {
+            LightChannel::Sky => light >> 4,
+            LightChannel::Block => light & 0x0f,
+        }
+    }
+
+    /// Sets a light level, from 0 to 15.
+    pub fn set_light(&mut self, x: usize, y: usize, z: usize, channel: LightChannel, level: u8) {
+        let light = &mut self.light[Self::index(x, y, z)];
+        let level = level.min(0x0f);
+        *light = match channel {
+            LightChannel::Sky => (*light & 0x0f) | (level << 4),
+            LightChannel::Block => (*light & 0xf0) | level,
+        };
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.blocks.iter().all(|&block| block == AIR)
+    }
+
+    // Blocks are laid out x first, then z, then y, so that a horizontal
+    // layer of the chunk is contiguous in memory.
+    fn index(x: usize, y: usize, z: usize) -> usize {
+        debug_assert!(x < CHUNK_WIDTH && y < CHUNK_HEIGHT && z < CHUNK_WIDTH);
+        (y * CHUNK_WIDTH + z) * CHUNK_WIDTH + x
+    }
+}
+
+impl Default for Chunk {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn negative_blocks_belong_to_negative_chunks() {
+        assert_eq!(
+            ChunkPos::from_block_pos(uv::IVec3::new(0, 0, 0)),
+            ChunkPos::new(0, 0)
+        );
+        assert_eq!(
+            ChunkPos::from_block_pos(uv::IVec3::new(15, 0, 15)),
+            ChunkPos::new(0, 0)
+        );
+        assert_eq!(
+            ChunkPos::from_block_pos(uv::IVec3::new(16, 0, -1)),
+            ChunkPos::new(1, -1)
+        );
+        assert_eq!(
+            ChunkPos::from_block_pos(uv::IVec3::new(-16, 0, -17)),
+            ChunkPos::new(-1, -2)
+        );
+    }
+
+    #[test]
+    fn local_positions_wrap_within_the_chunk() {
+        assert_eq!(
+            local_block_pos(uv::IVec3::new(-1, 5, -16)),
+            Some((15, 5, 0))
+        );
+        assert_eq!(
+            local_block_pos(uv::IVec3::new(17, 255, 31)),
+            Some((1, 255, 15))
+        );
+        assert_eq!(local_block_pos(uv::IVec3::new(0, -1, 0)), None);
+        assert_eq!(local_block_pos(uv::IVec3::new(0, 256, 0)), None);
+    }
+
+    #[test]
+    fn light_channels_are_stored_separately() {
+        let mut chunk = Chunk::new();
+        chunk.set_light(3, 4, 5, LightChannel::Sky, 12);
+        chunk.set_light(3, 4, 5, LightChannel::Block, 7);
+        assert_eq!(chunk.get_light(3, 4, 5, LightChannel::Sky), 12);
+        assert_eq!(chunk.get_light(3, 4, 5, LightChannel::Block), 7);
+
+        chunk.set_light(3, 4, 5, LightChannel::Sky, 20);
+        assert_eq!(chunk.get_light(3, 4, 5, LightChannel::Sky), 15);
+        assert_eq!(chunk.get_light(3, 4, 5, LightChannel::Block), 7);
+    }
+}
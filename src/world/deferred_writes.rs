@@ -0,0 +1,187 @@
+use std::collections::{HashMap, HashSet};
+
+use ultraviolet as uv;
+
+use super::chunk::{self, BlockId, Chunk, ChunkPos, AIR};
+
+/// A block placed by world generation, in world block coordinates.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BlockWrite {
+    pub pos: uv::IVec3,
+    pub block: BlockId,
+    /// Whether the write replaces whatever is already there. Otherwise it only
+    /// fills air.
+    pub overwrite: bool,
+}
+
+impl BlockWrite {
+    pub fn new(pos: uv::IVec3, block: BlockId, overwrite: bool) -> Self {
+        Self {
+            pos,
+            block,
+            overwrite,
+        }
+    }
+
+    /// Applies the write to the chunk containing it. Writes outside the chunk
+    /// are ignored.
+    pub fn apply(&self, chunk: &mut Chunk, chunk_pos: ChunkPos) -> bool {
+        if ChunkPos::from_block_pos(self.pos) != chunk_pos {
+            return false;
+        }
+        let Some((x, y, z)) = chunk::local_block_pos(self.pos) else {
+            return false;
+        };
+        if !self.overwrite && chunk.get_block(x, y, z) != AIR {
+            return false;
+        }
+        chunk.set_block(x, y, z, self.block);
+        true
+    }
+}
+
+/// Blocks that features generated in one chunk place into its neighbours.
+///
+/// Writes are keyed by the chunk they land in and the chunk that produced
+/// them. That way a chunk gets the same overhanging leaves whether its
+/// neighbour was generated before or after it, and even if it's unloaded and
+/// generated again while the neighbour stays loaded. Generating the source
+/// chunk again replaces its writes rather than adding to them.
+///
+/// Writes from different neighbours can land on the same block. However they
+/// arrive, they're resolved as though applied in order of the source chunk's
+/// position, starting from the block generated there, so a chunk ends up the
+/// same whichever order it and its neighbours were generated in.
+#[derive(Default)]
+pub struct DeferredWrites {
+    targets: HashMap<ChunkPos, TargetWrites>,
+}
+
+/// Everything written into one chunk.
+#[derive(Default)]
+struct TargetWrites {
+    sources: HashMap<ChunkPos, Vec<BlockWrite>>,
+    /// The block generated at each written position, from before any writes
+    /// were applied. Only kept while the chunk is loaded.
+    generated: HashMap<uv::IVec3, BlockId>,
+}
+
+impl TargetWrites {
+    /// Sets each of the given blocks in the loaded chunk to what applying
+    /// every write to it in order of source position leaves there. Returns
+    /// the positions of the blocks that changed.
+    fn apply(
+        &mut self,
+        chunk: &mut Chunk,
+        positions: impl IntoIterator<Item = uv::IVec3>,
+    ) -> Vec<uv::IVec3> {
+        let mut sources: Vec<_> = self.sources.iter().collect();
+        sources.sort_by_key(|(source, _)| (source.x, source.z));
+
+        let mut written = Vec::new();
+        for pos in positions {
+            let Some((x, y, z)) = chunk::local_block_pos(pos) else {
+                continue;
+            };
+            let current = chunk.get_block(x, y, z);
+            let mut block = *self.generated.entry(pos).or_insert(current);
+            for write in sources.iter().flat_map(|(_, writes)| writes.iter()) {
+                if write.pos == pos && (write.overwrite || block == AIR) {
+                    block = write.block;
+                }
+            }
+            if block != current {
+                chunk.set_block(x, y, z, block);
+                written.push(pos);
+            }
+        }
+        written
+    }
+}
+
+impl DeferredWrites {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records the writes a chunk makes outside itself, grouped by the chunk
+    /// they land in. Returns the chunks written to.
+    pub fn record(&mut self, source: ChunkPos, writes: Vec<BlockWrite>) -> Vec<ChunkPos> {
+        let mut by_target: HashMap<ChunkPos, Vec<BlockWrite>> = HashMap::new();
+        for write in writes {
+            let target = ChunkPos::from_block_pos(write.pos);
+            if target != source {
+                by_target.entry(target).or_default().push(write);
+            }
+        }
+
+        let targets: Vec<ChunkPos> = by_target.keys().copied().collect();
+        for (target, writes) in by_target {
+            self.targets
+                .entry(target)
+                .or_default()
+                .sources
+                .insert(source, writes);
+        }
+        targets
+    }
+
+    /// Applies every write landing in a freshly generated chunk.
+    ///
+    /// Returns the positions of the blocks that were changed.
+    pub fn apply_to(&mut self, chunk: &mut Chunk, pos: ChunkPos) -> Vec<uv::IVec3> {
+        let Some(target) = self.targets.get_mut(&pos) else {
+            return Vec::new();
+        };
+        target.generated.clear();
+        let positions: HashSet<uv::IVec3> = target
+            .sources
+            .values()
+            .flatten()
+            .map(|write| write.pos)
+            .collect();
+        target.apply(chunk, positions)
+    }
+
+    /// Applies the writes one source chunk has just made into a target chunk
+    /// that's already loaded, resolving them against any other writes to the
+    /// same blocks.
+    ///
+    /// Returns the positions of the blocks that were changed.
+    pub fn apply_from(
+        &mut self,
+        chunk: &mut Chunk,
+        target: ChunkPos,
+        source: ChunkPos,
+    ) -> Vec<uv::IVec3> {
+        let Some(writes) = self.targets.get_mut(&target) else {
+            return Vec::new();
+        };
+        let positions: Vec<uv::IVec3> = writes.sources.get(&source).map_or(Vec::new(), |source| {
+            source.iter().map(|write| write.pos).collect()
+        });
+        writes.apply(chunk, positions)
+    }
+
+    /// Forgets writes that are no longer needed, given which chunks are
+    /// loaded.
+    ///
+    /// Writes from a chunk that's been unloaded are dropped once their target
+    /// is loaded and has them, since generating the source again records them
+    /// again. Writes into a chunk that isn't loaded are kept until it is.
+    pub fn prune(&mut self, is_loaded: impl Fn(ChunkPos) -> bool) {
+        self.targets.retain(|target, writes| {
+            if is_loaded(*target) {
+                writes.sources.retain(|source, _| is_loaded(*source));
+            } else {
+                writes.generated.clear();
+            }
+            !writes.sources.is_empty()
+        });
+    }
+
+    /// Number of chunks with writes waiting for them.
+    pub fn target_count(&self) -> usize {
+        self.targets.len()
+    }
+}
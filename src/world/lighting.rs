@@ -0,0 +1,551 @@
+use std::collections::{HashSet, VecDeque};
+
+use ultraviolet as uv;
+
+use crate::block::{face::Face, registry::BlockRegistry};
+
+use super::{
+    chunk::{self, BlockId, Chunk, ChunkPos, AIR, CHUNK_HEIGHT, CHUNK_WIDTH},
+    world::World,
+};
+
+pub const MAX_LIGHT: u8 = 15;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LightChannel {
+    /// Light from the sky, which falls straight down through air without
+    /// getting any dimmer.
+    Sky,
+    /// Light given off by blocks such as glowstone.
+    Block,
+}
+
+impl LightChannel {
+    pub const ALL: [LightChannel; 2] = [LightChannel::Sky, LightChannel::Block];
+}
+
+/// Spreads sky and block light through the world.
+///
+/// Light levels run from 0 to 15 and drop by one for every block travelled,
+/// and by more through blocks that let only some light through, like water and
+/// leaves. Opaque blocks stop light completely and are always dark.
+///
+/// Light is spread breadth first, from the brightest blocks outwards. When a
+/// block changes, the light that came from it is removed the same way before
+/// being spread again, so only the area the change can affect is visited.
+#[derive(Clone)]
+pub struct Lighting {
+    // Both indexed by block ID.
+    opacity: Vec<u8>,
+    emission: Vec<u8>,
+}
+
+/// Where light is being spread, either a lone chunk or the loaded world.
+trait LightVolume {
+    /// Returns None where light can't be stored: outside the volume, or above
+    /// or below the world.
+    fn get_block(&self, pos: uv::IVec3) -> Option<BlockId>;
+    fn get_light(&self, pos: uv::IVec3, channel: LightChannel) -> u8;
+    fn set_light(&mut self, pos: uv::IVec3, channel: LightChannel, level: u8);
+}
+
+impl Lighting {
+    pub fn from_registry(registry: &BlockRegistry) -> Self {
+        let mut opacity = Vec::new();
+        let mut emission = Vec::new();
+        for block in registry.blocks() {
+            let index = block.id as usize;
+            if index >= opacity.len() {
+                opacity.resize(index + 1, MAX_LIGHT);
+                emission.resize(index + 1, 0);
+            }
+            opacity[index] = if block.id == AIR {
+                0
+            } else if registry.is_opaque(block.id) {
+                MAX_LIGHT
+            } else {
+                1
+            };
+            emission[index] = block.light_emission;
+        }
+
+        Self { opacity, emission }
+    }
+
+    /// How many extra light levels are lost passing through a block.
+    pub fn opacity(&self, block: BlockId) -> u8 {
+        self.opacity
+            .get(block as usize)
+            .copied()
+            .unwrap_or(MAX_LIGHT)
+    }
+
+    pub fn emission(&self, block: BlockId) -> u8 {
+        self.emission.get(block as usize).copied().unwrap_or(0)
+    }
+
+    /// Lights a newly generated chunk by itself, as if nothing around it were
+    /// loaded. Light from and into its neighbours is added by `stitch_chunk`
+    /// once it's in the world.
+    pub fn light_chunk(&self, chunk: &mut Chunk) {
+        let mut volume = ChunkVolume { chunk };
+
+        // Sky light falls straight down until it hits something that isn't
+        // air. Everything it reaches is lit fully.
+        let mut sky_heights = [[CHUNK_HEIGHT; CHUNK_WIDTH]; CHUNK_WIDTH];
+        for (z, row) in sky_heights.iter_mut().enumerate() {
+            for (x, sky_height) in row.iter_mut().enumerate() {
+                for y in (0..CHUNK_HEIGHT).rev() {
+                    if self.opacity(volume.chunk.get_block(x, y, z)) > 0 {
+                        break;
+                    }
+                    volume
+                        .chunk
+                        .set_light(x, y, z, LightChannel::Sky, MAX_LIGHT);
+                    *sky_height = y;
+                }
+            }
+        }
+
+        // Only the edges of the fully lit area need spreading from: the
+        // lowest lit block in each column, and blocks beside a neighbouring
+        // column that's lit from lower down.
+        let mut sky_queue = VecDeque::new();
+        for z in 0..CHUNK_WIDTH {
+            for x in 0..CHUNK_WIDTH {
+                let sky_height = sky_heights[z][x];
+                let mut highest_neighbour = sky_height;
+                for (dx, dz) in [(-1, 0), (1, 0), (0, -1), (0, 1)] {
+                    let (nx, nz) = (x as i32 + dx, z as i32 + dz);
+                    if (0..CHUNK_WIDTH as i32).contains(&nx)
+                        && (0..CHUNK_WIDTH as i32).contains(&nz)
+                    {
+                        highest_neighbour =
+                            highest_neighbour.max(sky_heights[nz as usize][nx as usize]);
+                    }
+                }
+                for y in sky_height..highest_neighbour.min(CHUNK_HEIGHT - 1) + 1 {
+                    if y < CHUNK_HEIGHT {
+                        sky_queue.push_back(uv::IVec3::new(x as i32, y as i32, z as i32));
+                    }
+                }
+            }
+        }
+        self.propagate(&mut volume, LightChannel::Sky, sky_queue);
+
+        let mut block_queue = VecDeque::new();
+        for y in 0..CHUNK_HEIGHT {
+            for z in 0..CHUNK_WIDTH {
+                for x in 0..CHUNK_WIDTH {
+                    let emission = self.emission(volume.chunk.get_block(x, y, z));
+                    if emission > 0 {
+                        volume
+                            .chunk
+                            .set_light(x, y, z, LightChannel::Block, emission);
+                        block_queue.push_back(uv::IVec3::new(x as i32, y as i32, z as i32));
+                    }
+                }
+            }
+        }
+        self.propagate(&mut volume, LightChannel::Block, block_queue);
+    }
+
+    /// Spreads light both ways across the borders between a chunk that's just
+    /// been added to the world and its loaded neighbours.
+    ///
+    /// Returns the chunks whose light changed.
+    pub fn stitch_chunk(&self, world: &mut World, pos: ChunkPos) -> HashSet<ChunkPos> {
+        let origin = pos.origin();
+        let width = CHUNK_WIDTH as i32;
+        let mut volume = WorldVolume::new(world);
+
+        for channel in LightChannel::ALL {
+            let mut queue = VecDeque::new();
+            for i in 0..width {
+                // Blocks along each edge of the chunk, paired with the blocks
+                // just across the edge.
+                let edges = [
+                    (uv::IVec3::new(0, 0, i), uv::IVec3::new(-1, 0, i)),
+                    (uv::IVec3::new(width - 1, 0, i), uv::IVec3::new(width, 0, i)),
+                    (uv::IVec3::new(i, 0, 0), uv::IVec3::new(i, 0, -1)),
+                    (uv::IVec3::new(i, 0, width - 1), uv::IVec3::new(i, 0, width)),
+                ];
+                for (inside, outside) in edges {
+                    for y in 0..CHUNK_HEIGHT as i32 {
+                        for edge in [inside, outside] {
+                            let block_pos = origin + edge + uv::IVec3::new(0, y, 0);
+                            if volume.get_light(block_pos, channel) > 1 {
+                                queue.push_back(block_pos);
+                            }
+                        }
+                    }
+                }
+            }
+            self.propagate(&mut volume, channel, queue);
+        }
+
+        volume.changed
+    }
+
+    /// Updates light after the block at a position has changed.
+    ///
+    /// Returns the chunks whose light changed.
+    pub fn update_block(&self, world: &mut World, pos: uv::IVec3) -> HashSet<ChunkPos> {
+        let mut volume = WorldVolume::new(world);
+        let Some(block) = volume.get_block(pos) else {
+            return volume.changed;
+        };
+
+        for channel in LightChannel::ALL {
+            // Take away the light the block had, along with any light that
+            // spread from it, keeping track of the brighter light around the
+            // edges that has to be spread back in.
+            let mut relight = self.remove(&mut volume, channel, pos);
+
+            if channel == LightChannel::Block && self.emission(block) > 0 {
+                volume.set_light(pos, channel, self.emission(block));
+                relight.push_back(pos);
+            }
+            // Nothing's above the top of the world to let sky light in, so it
+            // has to be added here.
+            if channel == LightChannel::Sky
+                && pos.y == CHUNK_HEIGHT as i32 - 1
+                && self.opacity(block) == 0
+            {
+                volume.set_light(pos, channel, MAX_LIGHT);
+                relight.push_back(pos);
+            }
+            // Light can now spread into the block from around it.
+            for face in Face::ALL {
+                let neighbour = pos + face.offset();
+                if volume.get_light(neighbour, channel) > 0 {
+                    relight.push_back(neighbour);
+                }
+            }
+
+            self.propagate(&mut volume, channel, relight);
+        }
+
+        volume.changed
+    }
+
+    /// Spreads light outwards from every position in the queue.
+    fn propagate(
+        &self,
+        volume: &mut impl LightVolume,
+        channel: LightChannel,
+        mut queue: VecDeque<uv::IVec3>,
+    ) {
+        while let Some(pos) = queue.pop_front() {
+            let level = volume.get_light(pos, channel);
+            if level <= 1 {
+                continue;
+            }
+
+            for face in Face::ALL {
+                let neighbour = pos + face.offset();
+                let Some(block) = volume.get_block(neighbour) else {
+                    continue;
+                };
+                let Some(spread) = self.spread_level(channel, level, face, block) else {
+                    continue;
+                };
+                if spread > volume.get_light(neighbour, channel) {
+                    volume.set_light(neighbour, channel, spread);
+                    queue.push_back(neighbour);
+                }
+            }
+        }
+    }
+
+    /// Darkens the light at a position and everything lit by it. Returns the
+    /// positions bordering the darkened area that are lit by something else,
+    /// which have to be spread from again to fill it back in.
+    fn remove(
+        &self,
+        volume: &mut impl LightVolume,
+        channel: LightChannel,
+        pos: uv::IVec3,
+    ) -> VecDeque<uv::IVec3> {
+        let mut relight = VecDeque::new();
+        let level = volume.get_light(pos, channel);
+        if level == 0 {
+            return relight;
+        }
+        volume.set_light(pos, channel, 0);
+
+        let mut queue = VecDeque::from([(pos, level)]);
+        while let Some((pos, level)) = queue.pop_front() {
+            for face in Face::ALL {
+                let neighbour = pos + face.offset();
+                let Some(block) = volume.get_block(neighbour) else {
+                    continue;
+                };
+                let neighbour_level = volume.get_light(neighbour, channel);
+                if neighbour_level == 0 {
+                    continue;
+                }
+
+                // Anything dimmer may have been lit from here, as may full
+                // sky light directly below.
+                let lit_from_here = neighbour_level < level
+                    || self.spread_level(channel, level, face, block) == Some(neighbour_level)
+                        && neighbour_level == MAX_LIGHT;
+                if lit_from_here {
+                    volume.set_light(neighbour, channel, 0);
+                    queue.push_back((neighbour, neighbour_level));
+
+                    // Light sources light themselves back up.
+                    let emission = self.emission(block);
+                    if channel == LightChannel::Block && emission > 0 {
+                        volume.set_light(neighbour, channel, emission);
+                        relight.push_back(neighbour);
+                    }
+                } else {
+                    relight.push_back(neighbour);
+                }
+            }
+        }
+
+        relight
+    }
+
+    /// The light reaching a block from a neighbour at `level`, travelling
+    /// towards `face`. Returns None if the block stops light.
+    fn spread_level(
+        &self,
+        channel: LightChannel,
+        level: u8,
+        face: Face,
+        block: BlockId,
+    ) -> Option<u8> {
+        let opacity = self.opacity(block);
+        if opacity >= MAX_LIGHT {
+            return None;
+        }
+        if channel == LightChannel::Sky
+            && face == Face::Bottom
+            && level == MAX_LIGHT
+            && opacity == 0
+        {
+            return Some(MAX_LIGHT);
+        }
+        Some(level.saturating_sub(1 + opacity))
+    }
+}
+
+/// A single chunk, in coordinates local to it.
+struct ChunkVolume<'a> {
+    chunk: &'a mut Chunk,
+}
+
+impl ChunkVolume<'_> {
+    fn local(pos: uv::IVec3) -> Option<(usize, usize, usize)> {
+        let width = CHUNK_WIDTH as i32;
+        if (0..width).contains(&pos.x) && (0..width).contains(&pos.z) {
+            chunk::local_block_pos(pos)
+        } else {
+            None
+        }
+    }
+}
+
+impl LightVolume for ChunkVolume<'_> {
+    fn get_block(&self, pos: uv::IVec3) -> Option<BlockId> {
+        let (x, y, z) = Self::local(pos)?;
+        Some(self.chunk.get_block(x, y, z))
+    }
+
+    fn get_light(&self, pos: uv::IVec3, channel: LightChannel) -> u8 {
+        Self::local(pos).map_or(0, |(x, y, z)| self.chunk.get_light(x, y, z, channel))
+    }
+
+    fn set_light(&mut self, pos: uv::IVec3, channel: LightChannel, level: u8) {
+        if let Some((x, y, z)) = Self::local(pos) {
+            self.chunk.set_light(x, y, z, channel, level);
+        }
+    }
+}
+
+/// Every loaded chunk, in world coordinates. Keeps track of which chunks
+/// have had their light changed.
+struct WorldVolume<'a> {
+    world: &'a mut World,
+    changed: HashSet<ChunkPos>,
+}
+
+impl<'a> WorldVolume<'a> {
+    fn new(world: &'a mut World) -> Self {
+        Self {
+            world,
+            changed: HashSet::new(),
+        }
+    }
+}
+
+impl LightVolume for WorldVolume<'_> {
+    fn get_block(&self, pos: uv::IVec3) -> Option<BlockId> {
+        chunk::local_block_pos(pos)?;
+        self.world.get_block(pos)
+    }
+
+    fn get_light(&self, pos: uv::IVec3, channel: LightChannel) -> u8 {
+        let Some((x, y, z)) = chunk::local_block_pos(pos) else {
+            return 0;
+        };
+        self.world
+            .chunk(ChunkPos::from_block_pos(pos))
+            .map_or(0, |chunk| chunk.get_light(x, y, z, channel))
+    }
+
+    fn set_light(&mut self, pos: uv::IVec3, channel: LightChannel, level: u8) {
+        let Some((x, y, z)) = chunk::local_block_pos(pos) else {
+            return;
+        };
+        let chunk_pos = ChunkPos::from_block_pos(pos);
+        // Checking first avoids copying chunks held by workers when nothing
+        // changes.
+        let unchanged = self
+            .world
+            .chunk(chunk_pos)
+            .is_none_or(|chunk| chunk.get_light(x, y, z, channel) == level);
+        if unchanged {
+            return;
+        }
+        if let Some(chunk) = self.world.chunk_mut(chunk_pos) {
+            chunk.set_light(x, y, z, channel, level);
+            self.changed.insert(chunk_pos);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const STONE: BlockId = 1;
+    const GLOWSTONE: BlockId = 2;
+    const GROUND_HEIGHT: usize = 64;
+
+    /// On the border between chunk (0, 0) and chunk (1, 0), just above the
+    /// ground.
+    const BORDER: uv::IVec3 = uv::IVec3::new(15, GROUND_HEIGHT as i32, 8);
+
+    fn lighting() -> Lighting {
+        let definitions = ron::from_str(
+            r#"#![enable(implicit_some)]
+            [
+                (id: 1, name: "stone", textures: All("stone")),
+                (id: 2, name: "glowstone", textures: All("glowstone"), light_emission: 15),
+            ]"#,
+        )
+        .unwrap();
+        let registry = BlockRegistry::from_definitions(definitions, |_| true).unwrap();
+        Lighting::from_registry(&registry)
+    }
+
+    fn region() -> Vec<ChunkPos> {
+        (-1..=2)
+            .flat_map(|x| (-1..=1).map(move |z| ChunkPos::new(x, z)))
+            .collect()
+    }
+
+    /// Flat stone ground with the given blocks placed on it, lit from
+    /// scratch the way freshly generated chunks are.
+    fn lit_from_scratch(lighting: &Lighting, blocks: &[(uv::IVec3, BlockId)]) -> World {
+        let mut world = World::new();
+        for pos in region() {
+            let mut chunk = Chunk::new();
+            for y in 0..GROUND_HEIGHT {
+                for z in 0..CHUNK_WIDTH {
+                    for x in 0..CHUNK_WIDTH {
+                        chunk.set_block(x, y, z, STONE);
+                    }
+                }
+            }
+            world.insert_chunk(pos, chunk);
+        }
+        for &(pos, block) in blocks {
+            assert!(world.set_block(pos, block));
+        }
+        for pos in region() {
+            lighting.light_chunk(world.chunk_mut(pos).unwrap());
+        }
+        for pos in region() {
+            lighting.stitch_chunk(&mut world, pos);
+        }
+        world
+    }
+
+    /// Changes a block in a lit world, updating its light.
+    fn place(lighting: &Lighting, world: &mut World, pos: uv::IVec3, block: BlockId) {
+        assert!(world.set_block(pos, block));
+        lighting.update_block(world, pos);
+    }
+
+    fn assert_same_light(world: &World, expected: &World) {
+        for (chunk_pos, chunk) in expected.chunks() {
+            let actual = world.chunk(*chunk_pos).unwrap();
+            for y in 0..CHUNK_HEIGHT {
+                for z in 0..CHUNK_WIDTH {
+                    for x in 0..CHUNK_WIDTH {
+                        for channel in LightChannel::ALL {
+                            assert_eq!(
+                                actual.get_light(x, y, z, channel),
+                                chunk.get_light(x, y, z, channel),
+                                "{:?} light at {:?}",
+                                channel,
+                                chunk_pos.origin() + uv::IVec3::new(x as i32, y as i32, z as i32)
+                            );
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn placing_and_removing_an_opaque_block_relights_under_it() {
+        let lighting = lighting();
+        let mut world = lit_from_scratch(&lighting, &[]);
+        let roof: Vec<_> = (-1..=1)
+            .flat_map(|dx| (-1..=1).map(move |dz| BORDER + uv::IVec3::new(dx, 4, dz)))
+            .map(|pos| (pos, STONE))
+            .collect();
+
+        for &(pos, block) in &roof {
+            place(&lighting, &mut world, pos, block);
+        }
+        let expected = lit_from_scratch(&lighting, &roof);
+        // The roof shades the ground below it on both sides of the border.
+        for (chunk_pos, x) in [(ChunkPos::new(0, 0), 15), (ChunkPos::new(1, 0), 0)] {
+            let shaded = expected.chunk(chunk_pos).unwrap();
+            assert!(shaded.get_light(x, GROUND_HEIGHT, 8, LightChannel::Sky) < MAX_LIGHT);
+        }
+        assert_same_light(&world, &expected);
+
+        for &(pos, _) in &roof {
+            place(&lighting, &mut world, pos, AIR);
+        }
+        assert_same_light(&world, &lit_from_scratch(&lighting, &[]));
+    }
+
+    #[test]
+    fn adding_and_removing_a_light_source_relights_around_it() {
+        let lighting = lighting();
+        let mut world = lit_from_scratch(&lighting, &[]);
+
+        place(&lighting, &mut world, BORDER, GLOWSTONE);
+        let expected = lit_from_scratch(&lighting, &[(BORDER, GLOWSTONE)]);
+        // Its light reaches across the border into the next chunk.
+        let across = expected.chunk(ChunkPos::new(1, 0)).unwrap();
+        assert_eq!(
+            across.get_light(0, GROUND_HEIGHT, 8, LightChannel::Block),
+            MAX_LIGHT - 1
+        );
+        assert_same_light(&world, &expected);
+
+        place(&lighting, &mut world, BORDER, AIR);
+        assert_same_light(&world, &lit_from_scratch(&lighting, &[]));
+    }
+}
@@ -0,0 +1,6 @@
+pub mod chunk;
+pub mod deferred_writes;
+pub mod lighting;
+pub mod neighbourhood;
+#[allow(clippy::module_inception)]
+pub mod world;
@@ -0,0 +1,129 @@
+use std::sync::Arc;
+
+use super::{
+    chunk::{BlockId, Chunk, ChunkPos, AIR, CHUNK_HEIGHT, CHUNK_WIDTH},
+    lighting::{LightChannel, MAX_LIGHT},
+    world::World,
+};
+
+/// A chunk together with the eight chunks surrounding it.
+///
+/// Lets code working on one chunk look past its edges and corners.
+pub struct ChunkNeighbourhood<'a> {
+    pub pos: ChunkPos,
+    // Indexed by [dx + 1][dz + 1], so the center chunk is at [1][1].
+    chunks: [[Option<&'a Chunk>; 3]; 3],
+}
+
+impl<'a> ChunkNeighbourhood<'a> {
+    /// Creates a neighbourhood from a lookup for the chunk at each offset
+    /// from `pos`, with both offsets in the range -1..=1.
+    pub fn new(
+        pos: ChunkPos,
+        center: &'a Chunk,
+        mut neighbour: impl FnMut(i32, i32) -> Option<&'a Chunk>,
+    ) -> Self {
+        let mut chunks = [[None; 3]; 3];
+        for (dx, column) in (-1..=1).zip(chunks.iter_mut()) {
+            for (dz, chunk) in (-1..=1).zip(column.iter_mut()) {
+                *chunk = neighbour(dx, dz);
+            }
+        }
+        chunks[1][1] = Some(center);
+
+        Self { pos, chunks }
+    }
+
+    /// Returns None if the chunk at `pos` isn't loaded. Neighbours that
+    /// aren't loaded are left empty.
+    pub fn from_world(world: &'a World, pos: ChunkPos) -> Option<Self> {
+        let center = world.chunk(pos)?;
+        Some(Self::new(pos, center, |dx, dz| {
+            world.chunk(pos.offset(dx, dz))
+        }))
+    }
+
+    pub fn center(&self) -> &'a Chunk {
+        self.chunks[1][1].expect("Neighbourhood is missing its center chunk.")
+    }
+
+    /// Gets a block in coordinates local to the center chunk, which may be up
+    /// to one chunk outside it horizontally.
+    ///
+    /// Returns None for blocks in a neighbour that isn't loaded, or below the
+    /// world. Blocks above the world are air.
+    pub fn get_block(&self, x: i32, y: i32, z: i32) -> Option<BlockId> {
+        if y < 0 {
+            return None;
+        }
+        if y >= CHUNK_HEIGHT as i32 {
+            return Some(AIR);
+        }
+
+        let width = CHUNK_WIDTH as i32;
+        let chunk =
+            self.chunks[(x.div_euclid(width) + 1) as usize][(z.div_euclid(width) + 1) as usize]?;
+        Some(chunk.get_block(
+            x.rem_euclid(width) as usize,
+            y as usize,
+            z.rem_euclid(width) as usize,
+        ))
+    }
+
+    /// Gets a light level in the same coordinates as `get_block`.
+    ///
+    /// Above the world is lit fully by the sky and not at all by blocks.
+    pub fn get_light(&self, x: i32, y: i32, z: i32, channel: LightChannel) -> Option<u8> {
+        if y < 0 {
+            return None;
+        }
+        if y >= CHUNK_HEIGHT as i32 {
+            return Some(match channel {
+                LightChannel::Sky => MAX_LIGHT,
+                LightChannel::Block => 0,
+            });
+        }
+
+        let width = CHUNK_WIDTH as i32;
+        let chunk =
+            self.chunks[(x.div_euclid(width) + 1) as usize][(z.div_euclid(width) + 1) as usize]?;
+        Some(chunk.get_light(
+            x.rem_euclid(width) as usize,
+            y as usize,
+            z.rem_euclid(width) as usize,
+            channel,
+        ))
+    }
+}
+
+/// A chunk and its neighbours as they were at one moment, which can be sent
+/// to another thread and looked at while the world changes.
+pub struct NeighbourhoodSnapshot {
+    pub pos: ChunkPos,
+    // Indexed the same way as `ChunkNeighbourhood`.
+    chunks: [[Option<Arc<Chunk>>; 3]; 3],
+}
+
+impl NeighbourhoodSnapshot {
+    /// Returns None if the chunk at `pos` isn't loaded.
+    pub fn from_world(world: &World, pos: ChunkPos) -> Option<Self> {
+        world.shared_chunk(pos)?;
+        let mut chunks: [[Option<Arc<Chunk>>; 3]; 3] = Default::default();
+        for (dx, column) in (-1..=1).zip(chunks.iter_mut()) {
+            for (dz, chunk) in (-1..=1).zip(column.iter_mut()) {
+                *chunk = world.shared_chunk(pos.offset(dx, dz));
+            }
+        }
+
+        Some(Self { pos, chunks })
+    }
+
+    pub fn neighbourhood(&self) -> ChunkNeighbourhood<'_> {
+        let center = self.chunks[1][1]
+            .as_deref()
+            .expect("Snapshot is missing its center chunk.");
+        ChunkNeighbourhood::new(self.pos, center, |dx, dz| {
+            self.chunks[(dx + 1) as usize][(dz + 1) as usize].as_deref()
+        })
+    }
+}
@@ -0,0 +1,292 @@
+use std::{collections::HashMap, sync::Arc};
+
+use ultraviolet as uv;
+
+use super::{
+    chunk::{self, BlockId, Chunk, ChunkPos, AIR},
+    deferred_writes::{BlockWrite, DeferredWrites},
+};
+
+/// The set of loaded chunks, addressed in world block coordinates.
+///
+/// Chunks are shared so that background workers can hold on to a snapshot of
+/// one while the world keeps changing. Changing a chunk that's also held
+/// elsewhere copies it first.
+#[derive(Default)]
+pub struct World {
+    chunks: HashMap<ChunkPos, Arc<Chunk>>,
+    deferred_writes: DeferredWrites,
+}
+
+impl World {
+    pub fn new() -> Self {
+        Self {
+            chunks: HashMap::new(),
+            deferred_writes: DeferredWrites::new(),
+        }
+    }
+
+    /// Gets the block at a world position.
+    ///
+    /// Positions above or below the world are air. Returns None if the chunk
+    /// containing the position isn't loaded.
+    pub fn get_block(&self, pos: uv::IVec3) -> Option<BlockId> {
+        let chunk = self.chunks.get(&ChunkPos::from_block_pos(pos))?;
+        match chunk::local_block_pos(pos) {
+            Some((x, y, z)) => Some(chunk.get_block(x, y, z)),
+            None => Some(AIR),
+        }
+    }
+
+    /// Sets the block at a world position.
+    ///
+    /// Returns false if the position is outside the world or its chunk isn't
+    /// loaded, in which case nothing is changed.
+    pub fn set_block(&mut self, pos: uv::IVec3, block: BlockId) -> bool {
+        let Some((x, y, z)) = chunk::local_block_pos(pos) else {
+            return false;
+        };
+        match self.chunks.get_mut(&ChunkPos::from_block_pos(pos)) {
+            Some(chunk) => {
+                Arc::make_mut(chunk).set_block(x, y, z, block);
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn chunk(&self, pos: ChunkPos) -> Option<&Chunk> {
+        self.chunks.get(&pos).map(|chunk| chunk.as_ref())
+    }
+
+    /// A handle to a chunk which stays valid however the world changes.
+    pub fn shared_chunk(&self, pos: ChunkPos) -> Option<Arc<Chunk>> {
+        self.chunks.get(&pos).cloned()
+    }
+
+    pub fn chunk_mut(&mut self, pos: ChunkPos) -> Option<&mut Chunk> {
+        self.chunks.get_mut(&pos).map(Arc::make_mut)
+    }
+
+    /// Inserts a chunk, returning the chunk previously at that position.
+    pub fn insert_chunk(&mut self, pos: ChunkPos, chunk: Chunk) -> Option<Arc<Chunk>> {
+        self.chunks.insert(pos, Arc::new(chunk))
+    }
+
+    /// Inserts a freshly generated chunk, along with the blocks its features
+    /// place into neighbouring chunks.
+    ///
+    /// Writes from neighbours generated earlier are applied to the chunk
+    /// first. Its own writes go straight into neighbours that are already
+    /// loaded, and are kept for ones that aren't so they're applied when those
+    /// get generated.
+    ///
+    /// Returns the positions of every block written, in the chunk or its
+    /// neighbours, so they can be lit and meshed again.
+    pub fn insert_generated_chunk(
+        &mut self,
+        pos: ChunkPos,
+        mut chunk: Chunk,
+        overflow: Vec<BlockWrite>,
+    ) -> Vec<uv::IVec3> {
+        let mut written = self.deferred_writes.apply_to(&mut chunk, pos);
+        self.chunks.insert(pos, Arc::new(chunk));
+
+        for target in self.deferred_writes.record(pos, overflow) {
+            let Some(target_chunk) = self.chunks.get_mut(&target).map(Arc::make_mut) else {
+                continue;
+            };
+            written.extend(self.deferred_writes.apply_from(target_chunk, target, pos));
+        }
+        self.prune_deferred_writes();
+        written
+    }
+
+    pub fn deferred_writes(&self) -> &DeferredWrites {
+        &self.deferred_writes
+    }
+
+    pub fn remove_chunk(&mut self, pos: ChunkPos) -> Option<Arc<Chunk>> {
+        let chunk = self.chunks.remove(&pos);
+        self.prune_deferred_writes();
+        chunk
+    }
+
+    fn prune_deferred_writes(&mut self) {
+        let chunks = &self.chunks;
+        self.deferred_writes.prune(|pos| chunks.contains_key(&pos));
+    }
+
+    pub fn is_chunk_loaded(&self, pos: ChunkPos) -> bool {
+        self.chunks.contains_key(&pos)
+    }
+
+    pub fn chunks(&self) -> impl Iterator<Item = (&ChunkPos, &Chunk)> {
+        self.chunks.iter().map(|(pos, chunk)| (pos, chunk.as_ref()))
+    }
+
+    pub fn chunk_count(&self) -> usize {
+        self.chunks.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const STONE: BlockId = 1;
+    const LOG: BlockId = 2;
+    const LEAVES: BlockId = 3;
+
+    const TARGET: ChunkPos = ChunkPos { x: 0, z: 0 };
+    /// Two chunks whose features reach into `TARGET`. `WEST` comes first in
+    /// source order.
+    const NORTH: ChunkPos = ChunkPos { x: 0, z: -1 };
+    const WEST: ChunkPos = ChunkPos { x: -1, z: 0 };
+
+    /// Where both neighbours write into air, and where they write into stone.
+    const INTO_AIR: uv::IVec3 = uv::IVec3::new(0, 70, 0);
+    const INTO_STONE: uv::IVec3 = uv::IVec3::new(0, 60, 0);
+
+    fn world_around_origin() -> World {
+        let mut world = World::new();
+        for x in -1..=1 {
+            for z in -1..=1 {
+                world.insert_chunk(ChunkPos::new(x, z), Chunk::new());
+            }
+        }
+        world
+    }
+
+    #[test]
+    fn blocks_either_side_of_a_chunk_boundary_are_separate() {
+        let mut world = world_around_origin();
+        assert!(world.set_block(uv::IVec3::new(15, 10, 0), STONE));
+        assert_eq!(world.get_block(uv::IVec3::new(15, 10, 0)), Some(STONE));
+        assert_eq!(world.get_block(uv::IVec3::new(16, 10, 0)), Some(AIR));
+
+        let chunk = world.chunk(ChunkPos::new(0, 0)).unwrap();
+        assert_eq!(chunk.get_block(15, 10, 0), STONE);
+        assert!(world.chunk(ChunkPos::new(1, 0)).unwrap().is_empty());
+    }
+
+    #[test]
+    fn negative_coordinates_use_the_chunk_below_zero() {
+        let mut world = world_around_origin();
+        assert!(world.set_block(uv::IVec3::new(-1, 0, -16), STONE));
+        assert_eq!(world.get_block(uv::IVec3::new(-1, 0, -16)), Some(STONE));
+        assert_eq!(world.get_block(uv::IVec3::new(0, 0, -16)), Some(AIR));
+        assert_eq!(world.get_block(uv::IVec3::new(-1, 0, -17)), None);
+
+        let chunk = world.chunk(ChunkPos::new(-1, -1)).unwrap();
+        assert_eq!(chunk.get_block(15, 0, 0), STONE);
+        assert!(world.chunk(ChunkPos::new(0, 0)).unwrap().is_empty());
+    }
+
+    #[test]
+    fn unloaded_chunks_and_positions_outside_the_world_are_left_alone() {
+        let mut world = world_around_origin();
+        assert!(!world.set_block(uv::IVec3::new(48, 0, 0), STONE));
+        assert_eq!(world.get_block(uv::IVec3::new(48, 0, 0)), None);
+
+        assert!(!world.set_block(uv::IVec3::new(0, -1, 0), STONE));
+        assert!(!world.set_block(uv::IVec3::new(0, 256, 0), STONE));
+        assert_eq!(world.get_block(uv::IVec3::new(0, 256, 0)), Some(AIR));
+    }
+
+    #[test]
+    fn changing_a_shared_chunk_leaves_the_snapshot_alone() {
+        let mut world = world_around_origin();
+        let snapshot = world.shared_chunk(ChunkPos::new(0, 0)).unwrap();
+        world.set_block(uv::IVec3::new(1, 2, 3), STONE);
+        assert_eq!(snapshot.get_block(1, 2, 3), AIR);
+        assert_eq!(world.get_block(uv::IVec3::new(1, 2, 3)), Some(STONE));
+    }
+
+    /// Generates a chunk the same way every time, along with the writes its
+    /// features make into `TARGET`.
+    fn generate(pos: ChunkPos) -> (Chunk, Vec<BlockWrite>) {
+        let mut chunk = Chunk::new();
+        chunk.set_block(0, 60, 0, STONE);
+        let overflow = match pos {
+            WEST => vec![
+                BlockWrite::new(INTO_AIR, LEAVES, false),
+                BlockWrite::new(INTO_STONE, LEAVES, false),
+            ],
+            NORTH => vec![
+                BlockWrite::new(INTO_AIR, LOG, false),
+                BlockWrite::new(INTO_STONE, LOG, true),
+            ],
+            _ => Vec::new(),
+        };
+        (chunk, overflow)
+    }
+
+    fn load_in_order(order: &[ChunkPos]) -> World {
+        let mut world = World::new();
+        for &pos in order {
+            let (chunk, overflow) = generate(pos);
+            world.insert_generated_chunk(pos, chunk, overflow);
+        }
+        world
+    }
+
+    #[test]
+    fn writes_resolve_the_same_whichever_chunk_loads_first() {
+        let orders = [
+            [TARGET, NORTH, WEST],
+            [TARGET, WEST, NORTH],
+            [NORTH, TARGET, WEST],
+            [WEST, TARGET, NORTH],
+            [NORTH, WEST, TARGET],
+            [WEST, NORTH, TARGET],
+        ];
+        for order in orders {
+            let world = load_in_order(&order);
+            // The first source's leaves fill the air, and only the other
+            // source's log is allowed to replace stone.
+            assert_eq!(world.get_block(INTO_AIR), Some(LEAVES), "{:?}", order);
+            assert_eq!(world.get_block(INTO_STONE), Some(LOG), "{:?}", order);
+        }
+    }
+
+    #[test]
+    fn writes_into_a_loaded_chunk_report_what_changed() {
+        let mut world = load_in_order(&[TARGET, NORTH]);
+        let (chunk, overflow) = generate(WEST);
+        // The log in stone stays put, since the leaves only fill air.
+        assert_eq!(
+            world.insert_generated_chunk(WEST, chunk, overflow),
+            vec![INTO_AIR]
+        );
+    }
+
+    #[test]
+    fn writes_are_kept_until_their_target_has_them() {
+        let mut world = load_in_order(&[WEST]);
+        world.remove_chunk(WEST);
+        assert_eq!(world.deferred_writes().target_count(), 1);
+
+        let (chunk, overflow) = generate(TARGET);
+        world.insert_generated_chunk(TARGET, chunk, overflow);
+        assert_eq!(world.get_block(INTO_AIR), Some(LEAVES));
+        assert_eq!(world.deferred_writes().target_count(), 0);
+    }
+
+    #[test]
+    fn writes_are_kept_while_their_source_is_loaded() {
+        let mut world = load_in_order(&[WEST, TARGET]);
+        assert_eq!(world.deferred_writes().target_count(), 1);
+
+        // Generating the target again while its neighbour is still loaded
+        // puts the neighbour's leaves back.
+        world.remove_chunk(TARGET);
+        let (chunk, overflow) = generate(TARGET);
+        world.insert_generated_chunk(TARGET, chunk, overflow);
+        assert_eq!(world.get_block(INTO_AIR), Some(LEAVES));
+
+        world.remove_chunk(WEST);
+        assert_eq!(world.deferred_writes().target_count(), 0);
+        assert_eq!(world.get_block(INTO_AIR), Some(LEAVES));
+    }
+}
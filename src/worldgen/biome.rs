@@ -0,0 +1,119 @@
+use crate::{
+    block::registry::{BlockRegistry, BlockRegistryError},
+    world::chunk::{BiomeId, BlockId},
+};
+
+pub struct Biome {
+    pub name: &'static str,
+    /// Where the biome sits on the climate map. Both range from -1 to 1, and
+    /// each point on the map takes the closest biome.
+    pub temperature: f64,
+    pub humidity: f64,
+    /// Average surface height, and how far hills rise and fall around it.
+    pub base_height: f64,
+    pub height_amplitude: f64,
+    /// Block covering the surface, and the blocks between it and stone.
+    pub surface: BlockId,
+    pub filler: BlockId,
+    /// Chance of each surface block being decorated, e.g. with a tree.
+    pub decoration_density: f64,
+}
+
+/// Every biome, indexed by ID.
+pub struct Biomes {
+    biomes: Vec<Biome>,
+}
+
+impl Biomes {
+    pub fn from_registry(registry: &BlockRegistry) -> Result<Self, BlockRegistryError> {
+        let grass = registry.require("grass")?;
+        let dirt = registry.require("dirt")?;
+        let sand = registry.require("sand")?;
+        let sandstone = registry.require("sandstone")?;
+        let snow = registry.require("snow")?;
+
+        Ok(Self {
+            biomes: vec![
+                Biome {
+                    name: "plains",
+                    temperature: 0.3,
+                    humidity: 0.0,
+                    base_height: 68.0,
+                    height_amplitude: 8.0,
+                    surface: grass,
+                    filler: dirt,
+                    decoration_density: 0.002,
+                },
+                Biome {
+                    name: "forest",
+                    temperature: 0.2,
+                    humidity: 0.6,
+                    base_height: 70.0,
+                    height_amplitude: 14.0,
+                    surface: grass,
+                    filler: dirt,
+                    decoration_density: 0.03,
+                },
+                Biome {
+                    name: "desert",
+                    temperature: 0.8,
+                    humidity: -0.6,
+                    base_height: 67.0,
+                    height_amplitude: 6.0,
+                    surface: sand,
+                    filler: sandstone,
+                    decoration_density: 0.004,
+                },
+                Biome {
+                    name: "tundra",
+                    temperature: -0.7,
+                    humidity: 0.0,
+                    base_height: 69.0,
+                    height_amplitude: 10.0,
+                    surface: snow,
+                    filler: dirt,
+                    decoration_density: 0.005,
+                },
+                Biome {
+                    name: "mountains",
+                    temperature: -0.2,
+                    humidity: -0.6,
+                    base_height: 90.0,
+                    height_amplitude: 45.0,
+                    surface: grass,
+                    filler: dirt,
+                    decoration_density: 0.008,
+                },
+            ],
+        })
+    }
+
+    pub fn all(&self) -> &[Biome] {
+        &self.biomes
+    }
+
+    pub fn get(&self, id: BiomeId) -> &Biome {
+        &self.biomes[id as usize]
+    }
+
+    pub fn id(&self, name: &str) -> Option<BiomeId> {
+        self.biomes
+            .iter()
+            .position(|biome| biome.name == name)
+            .map(|id| id as BiomeId)
+    }
+
+    /// The biome closest to a point on the climate map.
+    pub fn closest(&self, temperature: f64, humidity: f64) -> BiomeId {
+        let distance = |biome: &Biome| {
+            (biome.temperature - temperature).powi(2) + (biome.humidity - humidity).powi(2)
+        };
+        let mut closest = 0;
+        for (id, biome) in self.biomes.iter().enumerate() {
+            if distance(biome) < distance(&self.biomes[closest]) {
+                closest = id;
+            }
+        }
+        closest as BiomeId
+    }
+}
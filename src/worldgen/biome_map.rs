@@ -0,0 +1,107 @@
+use noise::{Fbm, MultiFractal, NoiseFn, Perlin};
+
+use crate::world::chunk::{BiomeId, ChunkPos, CHUNK_WIDTH};
+
+use super::{biome::Biomes, seed};
+
+const TEMPERATURE_SALT: u32 = 10;
+const HUMIDITY_SALT: u32 = 11;
+
+/// Terrain shape is blended between biomes within this many blocks, so
+/// there are no cliffs where biomes meet.
+const BLEND_RADIUS: i32 = 8;
+/// Spacing of the points sampled when blending.
+const BLEND_STEP: i32 = 4;
+
+/// Biome and terrain shape of each column of a chunk.
+pub struct ChunkBiomes {
+    // Each laid out x first, then z.
+    pub ids: [BiomeId; CHUNK_WIDTH * CHUNK_WIDTH],
+    pub base_heights: [f64; CHUNK_WIDTH * CHUNK_WIDTH],
+    pub height_amplitudes: [f64; CHUNK_WIDTH * CHUNK_WIDTH],
+}
+
+/// Picks biomes from temperature and humidity noise.
+pub struct BiomeMap {
+    biomes: Biomes,
+    temperature_noise: Fbm<Perlin>,
+    humidity_noise: Fbm<Perlin>,
+}
+
+impl BiomeMap {
+    pub fn new(world_seed: u64, biomes: Biomes) -> Self {
+        let climate_noise = |salt| {
+            Fbm::<Perlin>::new(seed::derive_seed(world_seed, salt))
+                .set_octaves(3)
+                .set_frequency(1.0 / 512.0)
+                .set_lacunarity(2.0)
+        };
+
+        Self {
+            biomes,
+            temperature_noise: climate_noise(TEMPERATURE_SALT),
+            humidity_noise: climate_noise(HUMIDITY_SALT),
+        }
+    }
+
+    pub fn biomes(&self) -> &Biomes {
+        &self.biomes
+    }
+
+    pub fn biome_at(&self, x: i32, z: i32) -> BiomeId {
+        let point = [x as f64, z as f64];
+        // Fbm rarely strays far from 0, so stretch it to cover the climate map.
+        let temperature = (self.temperature_noise.get(point) * 2.0).clamp(-1.0, 1.0);
+        let humidity = (self.humidity_noise.get(point) * 2.0).clamp(-1.0, 1.0);
+        self.biomes.closest(temperature, humidity)
+    }
+
+    pub fn sample_chunk(&self, pos: ChunkPos) -> ChunkBiomes {
+        let origin = pos.origin();
+
+        // Sample biomes on a grid aligned to world coordinates rather than to
+        // the chunk, so neighbouring chunks blend from the same samples.
+        let grid_min_x = (origin.x - BLEND_RADIUS).div_euclid(BLEND_STEP) * BLEND_STEP;
+        let grid_min_z = (origin.z - BLEND_RADIUS).div_euclid(BLEND_STEP) * BLEND_STEP;
+        let grid_size = ((CHUNK_WIDTH as i32 + 2 * BLEND_RADIUS) / BLEND_STEP + 2) as usize;
+        let mut grid = Vec::with_capacity(grid_size * grid_size);
+        for gz in 0..grid_size {
+            for gx in 0..grid_size {
+                let x = grid_min_x + gx as i32 * BLEND_STEP;
+                let z = grid_min_z + gz as i32 * BLEND_STEP;
+                grid.push((x, z, self.biomes.get(self.biome_at(x, z))));
+            }
+        }
+
+        let mut columns = ChunkBiomes {
+            ids: [0; CHUNK_WIDTH * CHUNK_WIDTH],
+            base_heights: [0.0; CHUNK_WIDTH * CHUNK_WIDTH],
+            height_amplitudes: [0.0; CHUNK_WIDTH * CHUNK_WIDTH],
+        };
+        for z in 0..CHUNK_WIDTH {
+            for x in 0..CHUNK_WIDTH {
+                let (world_x, world_z) = (origin.x + x as i32, origin.z + z as i32);
+                let index = z * CHUNK_WIDTH + x;
+                columns.ids[index] = self.biome_at(world_x, world_z);
+
+                // Weight samples by distance, fading to nothing at the blend
+                // radius.
+                let mut total_weight = 0.0;
+                let mut base_height = 0.0;
+                let mut height_amplitude = 0.0;
+                for &(sample_x, sample_z, biome) in &grid {
+                    let distance_squared =
+                        ((sample_x - world_x).pow(2) + (sample_z - world_z).pow(2)) as f64;
+                    let weight = (BLEND_RADIUS.pow(2) as f64 - distance_squared).max(0.0);
+                    total_weight += weight;
+                    base_height += biome.base_height * weight;
+                    height_amplitude += biome.height_amplitude * weight;
+                }
+                columns.base_heights[index] = base_height / total_weight;
+                columns.height_amplitudes[index] = height_amplitude / total_weight;
+            }
+        }
+
+        columns
+    }
+}
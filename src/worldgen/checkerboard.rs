@@ -0,0 +1,32 @@
+use crate::world::chunk::{BlockId, Chunk, ChunkPos, CHUNK_HEIGHT, CHUNK_WIDTH};
+
+use super::terrain_generator::TerrainGenerator;
+
+/// Debug generator making chunk borders easy to see: a floor whose block
+/// alternates from chunk to chunk, like a checkerboard.
+pub struct CheckerboardGenerator {
+    blocks: [BlockId; 2],
+    height: usize,
+}
+
+impl CheckerboardGenerator {
+    pub fn new(blocks: [BlockId; 2], height: usize) -> Self {
+        Self {
+            blocks,
+            height: height.min(CHUNK_HEIGHT - 1),
+        }
+    }
+}
+
+impl TerrainGenerator for CheckerboardGenerator {
+    fn generate_chunk(&self, pos: ChunkPos) -> Chunk {
+        let block = self.blocks[(pos.x + pos.z).rem_euclid(2) as usize];
+        let mut chunk = Chunk::new();
+        for z in 0..CHUNK_WIDTH {
+            for x in 0..CHUNK_WIDTH {
+                chunk.set_block(x, self.height, z, block);
+            }
+        }
+        chunk
+    }
+}
@@ -0,0 +1,226 @@
+use crate::world::{
+    chunk::{Chunk, ChunkPos, AIR, CHUNK_HEIGHT, CHUNK_WIDTH},
+    deferred_writes::BlockWrite,
+};
+
+use super::{biome::Biomes, feature::Feature, seed::WorldRng};
+
+const DECORATION_SALT: u32 = 40;
+
+/// Places features such as trees and boulders on generated terrain.
+///
+/// Every feature belongs to the chunk its base stands in, and is placed from
+/// a random generator seeded by that chunk alone, so a chunk always gets the
+/// same features. Parts of a feature that hang over into a neighbouring chunk
+/// are handed back rather than placed, to be applied to the neighbour through
+/// the world (see `World::insert_generated_chunk`).
+pub struct Decorator {
+    world_seed: u64,
+    features: Vec<Feature>,
+    /// For each biome, the chance of decorating a surface block and the
+    /// features it can choose from.
+    biome_features: Vec<BiomeFeatures>,
+}
+
+struct BiomeFeatures {
+    density: f64,
+    features: Vec<usize>,
+    total_weight: u32,
+}
+
+impl Decorator {
+    pub fn new(world_seed: u64, features: Vec<Feature>, biomes: &Biomes) -> Self {
+        let biome_features = biomes
+            .all()
+            .iter()
+            .map(|biome| {
+                let id = biomes.id(biome.name).unwrap();
+                let allowed: Vec<usize> = features
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, feature)| feature.weight > 0 && feature.biomes.contains(&id))
+                    .map(|(index, _)| index)
+                    .collect();
+                BiomeFeatures {
+                    density: biome.decoration_density,
+                    total_weight: allowed.iter().map(|&index| features[index].weight).sum(),
+                    features: allowed,
+                }
+            })
+            .collect();
+
+        Self {
+            world_seed,
+            features,
+            biome_features,
+        }
+    }
+
+    pub fn features(&self) -> &[Feature] {
+        &self.features
+    }
+
+    /// Decorates a chunk, returning the blocks that land outside it.
+    pub fn decorate(&self, chunk: &mut Chunk, pos: ChunkPos) -> Vec<BlockWrite> {
+        let mut rng = WorldRng::for_chunk(self.world_seed, DECORATION_SALT, pos);
+        let origin = pos.origin();
+
+        // Find every feature first, so earlier features can't change the
+        // ground under later ones.
+        let mut placements = Vec::new();
+        for z in 0..CHUNK_WIDTH {
+            for x in 0..CHUNK_WIDTH {
+                let biome = &self.biome_features[chunk.get_biome(x, z) as usize];
+                // Draw the same numbers whatever the column looks like, so
+                // one column can't shift the features in every column after it.
+                let roll = rng.next_f64();
+                let pick = rng.next_f64();
+                if roll >= biome.density || biome.total_weight == 0 {
+                    continue;
+                }
+
+                let Some(y) = surface_height(chunk, x, z) else {
+                    continue;
+                };
+                let feature = self.pick(biome, pick);
+                if !feature.on.contains(&chunk.get_block(x, y, z)) {
+                    continue;
+                }
+                placements.push((feature, origin + (x as i32, y as i32, z as i32).into()));
+            }
+        }
+
+        let mut overflow = Vec::new();
+        for (feature, ground) in placements {
+            for write in feature.place(&mut rng, ground) {
+                if !write.apply(chunk, pos) && ChunkPos::from_block_pos(write.pos) != pos {
+                    overflow.push(write);
+                }
+            }
+        }
+        overflow
+    }
+
+    fn pick(&self, biome: &BiomeFeatures, pick: f64) -> &Feature {
+        let mut remaining = (pick * biome.total_weight as f64) as u32;
+        for &index in &biome.features {
+            let feature = &self.features[index];
+            if remaining < feature.weight {
+                return feature;
+            }
+            remaining -= feature.weight;
+        }
+        &self.features[*biome.features.last().unwrap()]
+    }
+}
+
+/// The highest block in a column with air above it, if any.
+fn surface_height(chunk: &Chunk, x: usize, z: usize) -> Option<usize> {
+    (0..CHUNK_HEIGHT - 1)
+        .rev()
+        .find(|&y| chunk.get_block(x, y, z) != AIR)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use crate::{
+        world::world::World,
+        worldgen::{
+            biome::Biomes,
+            feature::Feature,
+            noise_terrain::tests::{generator, region, registry, shuffled, SEED},
+            terrain_generator::TerrainGenerator,
+        },
+    };
+
+    use super::*;
+
+    fn decorator(seed: u64) -> Decorator {
+        let registry = registry();
+        let biomes = Biomes::from_registry(&registry).unwrap();
+        let features = Feature::load_all("src/assets/features.ron", &registry, &biomes).unwrap();
+        Decorator::new(seed, features, &biomes)
+    }
+
+    /// Generates and decorates each chunk, keeping the blocks its features
+    /// place outside it.
+    fn decorate_all(
+        seed: u64,
+        positions: &[ChunkPos],
+    ) -> HashMap<ChunkPos, (Chunk, Vec<BlockWrite>)> {
+        let generator = generator(seed);
+        let decorator = decorator(seed);
+        positions
+            .iter()
+            .map(|&pos| {
+                let mut chunk = generator.generate_chunk(pos);
+                let overflow = decorator.decorate(&mut chunk, pos);
+                (pos, (chunk, overflow))
+            })
+            .collect()
+    }
+
+    fn load_in_order(order: &[ChunkPos]) -> World {
+        let mut decorated = decorate_all(SEED, order);
+        let mut world = World::new();
+        for pos in order {
+            let (chunk, overflow) = decorated.remove(pos).unwrap();
+            world.insert_generated_chunk(*pos, chunk, overflow);
+        }
+        world
+    }
+
+    #[test]
+    fn the_same_seed_places_the_same_features() {
+        let positions = region(1);
+        let expected = decorate_all(SEED, &positions);
+        let decorated = decorate_all(SEED, &shuffled(&positions, 1));
+        for (pos, (chunk, overflow)) in &expected {
+            assert!(
+                decorated[pos].0 == *chunk,
+                "chunk {:?} was decorated differently",
+                pos
+            );
+            assert_eq!(decorated[pos].1, *overflow, "chunk {:?}", pos);
+        }
+
+        // Features were placed at all.
+        let generator = generator(SEED);
+        assert!(positions
+            .iter()
+            .any(|&pos| generator.generate_chunk(pos) != expected[&pos].0));
+    }
+
+    #[test]
+    fn trees_on_chunk_edges_reach_into_neighbours_whichever_loads_first() {
+        let leaves = registry().id("oak_leaves").unwrap();
+        // Big enough to have trees near its chunk edges.
+        let forward = region(2);
+        let mut reversed = forward.clone();
+        reversed.reverse();
+        let worlds: Vec<World> = [forward.clone(), reversed, shuffled(&forward, 2)]
+            .iter()
+            .map(|order| load_in_order(order))
+            .collect();
+
+        // Leaves from a tree in one chunk ended up in another.
+        let crossed = decorate_all(SEED, &forward)
+            .into_values()
+            .flat_map(|(_, overflow)| overflow)
+            .filter(|write| forward.contains(&ChunkPos::from_block_pos(write.pos)))
+            .any(|write| write.block == leaves && worlds[0].get_block(write.pos) == Some(leaves));
+        assert!(crossed);
+
+        for world in &worlds[1..] {
+            for pos in &forward {
+                assert!(
+                    world.chunk(*pos) == worlds[0].chunk(*pos),
+                    "chunk {:?} has different blocks",
+                    pos
+                );
+            }
+        }
+    }
+}
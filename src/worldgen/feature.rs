@@ -0,0 +1,371 @@
+use std::{collections::HashMap, f64::consts::PI, fmt, fs};
+
+use log::info;
+use serde::Deserialize;
+use ultraviolet as uv;
+
+use crate::{
+    block::registry::BlockRegistry,
+    world::{
+        chunk::{BiomeId, BlockId, AIR},
+        deferred_writes::BlockWrite,
+    },
+};
+
+use super::{biome::Biomes, seed::WorldRng};
+
+/// A feature, as described in `src/assets/features.ron`.
+#[derive(Debug, Deserialize)]
+pub struct FeatureDefinition {
+    pub name: String,
+    pub biomes: Vec<String>,
+    pub on: Vec<String>,
+    pub weight: u32,
+    pub kind: FeatureKindDefinition,
+}
+
+#[derive(Debug, Deserialize)]
+pub enum FeatureKindDefinition {
+    Tree {
+        trunk: String,
+        leaves: String,
+        min_height: u32,
+        max_height: u32,
+        leaf_radius: u32,
+    },
+    Column {
+        block: String,
+        min_height: u32,
+        max_height: u32,
+    },
+    Boulder {
+        block: String,
+        min_radius: f64,
+        max_radius: f64,
+    },
+    Structure {
+        palette: HashMap<char, String>,
+        layers: Vec<Vec<String>>,
+    },
+}
+
+/// A feature with every block and biome name resolved.
+pub struct Feature {
+    pub name: String,
+    pub biomes: Vec<BiomeId>,
+    pub on: Vec<BlockId>,
+    pub weight: u32,
+    pub kind: FeatureKind,
+}
+
+pub enum FeatureKind {
+    Tree {
+        trunk: BlockId,
+        leaves: BlockId,
+        min_height: u32,
+        max_height: u32,
+        leaf_radius: u32,
+    },
+    Column {
+        block: BlockId,
+        min_height: u32,
+        max_height: u32,
+    },
+    Boulder {
+        block: BlockId,
+        min_radius: f64,
+        max_radius: f64,
+    },
+    /// Indexed by [y][z][x]. None leaves the existing block alone.
+    Structure {
+        blocks: Vec<Vec<Vec<Option<BlockId>>>>,
+    },
+}
+
+impl Feature {
+    /// Loads feature definitions from a RON file.
+    pub fn load_all(
+        path: &str,
+        registry: &BlockRegistry,
+        biomes: &Biomes,
+    ) -> Result<Vec<Self>, FeatureError> {
+        info!("Loading features from {}", path);
+        let source = fs::read_to_string(path).map_err(|error| FeatureError::Io {
+            path: path.to_string(),
+            error,
+        })?;
+        let definitions: Vec<FeatureDefinition> =
+            ron::from_str(&source).map_err(|error| FeatureError::Parse {
+                path: path.to_string(),
+                error,
+            })?;
+
+        let features = definitions
+            .into_iter()
+            .map(|definition| Self::from_definition(definition, registry, biomes))
+            .collect::<Result<Vec<_>, _>>()?;
+        info!("Loaded {} features", features.len());
+        Ok(features)
+    }
+
+    pub fn from_definition(
+        definition: FeatureDefinition,
+        registry: &BlockRegistry,
+        biomes: &Biomes,
+    ) -> Result<Self, FeatureError> {
+        let name = definition.name;
+        let block = |block: &str| {
+            registry
+                .id(block)
+                .ok_or_else(|| FeatureError::UnknownBlock {
+                    feature: name.clone(),
+                    block: block.to_string(),
+                })
+        };
+
+        let kind = match definition.kind {
+            FeatureKindDefinition::Tree {
+                trunk,
+                leaves,
+                min_height,
+                max_height,
+                leaf_radius,
+            } => FeatureKind::Tree {
+                trunk: block(&trunk)?,
+                leaves: block(&leaves)?,
+                min_height,
+                max_height: max_height.max(min_height),
+                leaf_radius,
+            },
+            FeatureKindDefinition::Column {
+                block: column,
+                min_height,
+                max_height,
+            } => FeatureKind::Column {
+                block: block(&column)?,
+                min_height,
+                max_height: max_height.max(min_height),
+            },
+            FeatureKindDefinition::Boulder {
+                block: boulder,
+                min_radius,
+                max_radius,
+            } => FeatureKind::Boulder {
+                block: block(&boulder)?,
+                min_radius,
+                max_radius: max_radius.max(min_radius),
+            },
+            FeatureKindDefinition::Structure { palette, layers } => {
+                let mut blocks = Vec::new();
+                for layer in &layers {
+                    let mut rows = Vec::new();
+                    for row in layer {
+                        let mut cells = Vec::new();
+                        for character in row.chars() {
+                            cells.push(match character {
+                                ' ' => None,
+                                '.' => Some(AIR),
+                                character => match palette.get(&character) {
+                                    Some(palette_block) => Some(block(palette_block)?),
+                                    None => {
+                                        return Err(FeatureError::UnknownPaletteCharacter {
+                                            feature: name.clone(),
+                                            character,
+                                        })
+                                    }
+                                },
+                            });
+                        }
+                        rows.push(cells);
+                    }
+                    blocks.push(rows);
+                }
+                FeatureKind::Structure { blocks }
+            }
+        };
+
+        let on = definition
+            .on
+            .iter()
+            .map(|ground| block(ground))
+            .collect::<Result<Vec<_>, _>>()?;
+        let feature_biomes = definition
+            .biomes
+            .iter()
+            .map(|biome| {
+                biomes.id(biome).ok_or_else(|| FeatureError::UnknownBiome {
+                    feature: name.clone(),
+                    biome: biome.clone(),
+                })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(Self {
+            name,
+            biomes: feature_biomes,
+            on,
+            weight: definition.weight,
+            kind,
+        })
+    }
+
+    /// Lists the blocks making up one instance of the feature, standing on
+    /// the surface block at `ground`.
+    pub fn place(&self, rng: &mut WorldRng, ground: uv::IVec3) -> Vec<BlockWrite> {
+        let mut writes = Vec::new();
+        let base = ground + uv::IVec3::unit_y();
+
+        match self.kind {
+            FeatureKind::Tree {
+                trunk,
+                leaves,
+                min_height,
+                max_height,
+                leaf_radius,
+            } => {
+                let height = (min_height + rng.below(max_height - min_height + 1)) as i32;
+                for y in 0..height {
+                    writes.push(BlockWrite::new(base + uv::IVec3::new(0, y, 0), trunk, true));
+                }
+
+                // Two wide layers around the top of the trunk, then two
+                // narrower ones capping it.
+                let radius = leaf_radius as i32;
+                for dy in -2..=1 {
+                    let layer_radius = if dy < 0 { radius } else { (radius - 1).max(1) };
+                    for dz in -layer_radius..=layer_radius {
+                        for dx in -layer_radius..=layer_radius {
+                            let is_corner = dx.abs() == layer_radius && dz.abs() == layer_radius;
+                            // Knock some corners off so trees aren't perfectly square.
+                            if is_corner && (dy == 1 || rng.next_f64() < 0.5) {
+                                continue;
+                            }
+                            let pos = base + uv::IVec3::new(dx, height - 1 + dy, dz);
+                            writes.push(BlockWrite::new(pos, leaves, false));
+                        }
+                    }
+                }
+            }
+            FeatureKind::Column {
+                block,
+                min_height,
+                max_height,
+            } => {
+                let height = (min_height + rng.below(max_height - min_height + 1)) as i32;
+                for y in 0..height {
+                    writes.push(BlockWrite::new(
+                        base + uv::IVec3::new(0, y, 0),
+                        block,
+                        false,
+                    ));
+                }
+            }
+            FeatureKind::Boulder {
+                block,
+                min_radius,
+                max_radius,
+            } => {
+                let radius = rng.range(min_radius, max_radius);
+                // Squash the boulder a little and sink it into the ground.
+                let vertical_radius = radius * rng.range(0.6, 1.0);
+                let extent = radius.ceil() as i32;
+                let angle = rng.range(0.0, 2.0 * PI);
+                for dy in -extent..=extent {
+                    for dz in -extent..=extent {
+                        for dx in -extent..=extent {
+                            // Rotate the lumpy x axis so boulders face different ways.
+                            let along = dx as f64 * angle.cos() + dz as f64 * angle.sin();
+                            let across = -dx as f64 * angle.sin() + dz as f64 * angle.cos();
+                            let distance = (along / (radius * 1.2)).powi(2)
+                                + (across / radius).powi(2)
+                                + (dy as f64 / vertical_radius).powi(2);
+                            if distance < 1.0 {
+                                writes.push(BlockWrite::new(
+                                    ground + uv::IVec3::new(dx, dy, dz),
+                                    block,
+                                    false,
+                                ));
+                            }
+                        }
+                    }
+                }
+            }
+            FeatureKind::Structure { ref blocks } => {
+                for (y, layer) in blocks.iter().enumerate() {
+                    for (z, row) in layer.iter().enumerate() {
+                        for (x, block) in row.iter().enumerate() {
+                            if let Some(block) = *block {
+                                let pos = ground + uv::IVec3::new(x as i32, y as i32, z as i32);
+                                writes.push(BlockWrite::new(pos, block, true));
+                            }
+                        }
+                    }
+                }
+            }
+        }
+
+        writes
+    }
+}
+
+#[derive(Debug)]
+pub enum FeatureError {
+    Io {
+        path: String,
+        error: std::io::Error,
+    },
+    Parse {
+        path: String,
+        error: ron::error::SpannedError,
+    },
+    UnknownBlock {
+        feature: String,
+        block: String,
+    },
+    UnknownBiome {
+        feature: String,
+        biome: String,
+    },
+    UnknownPaletteCharacter {
+        feature: String,
+        character: char,
+    },
+}
+
+impl fmt::Display for FeatureError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FeatureError::Io { path, error } => {
+                write!(f, "failed to read features at {}: {}", path, error)
+            }
+            FeatureError::Parse { path, error } => {
+                write!(f, "failed to parse features at {}: {}", path, error)
+            }
+            FeatureError::UnknownBlock { feature, block } => write!(
+                f,
+                "feature \"{}\" uses block \"{}\", which isn't defined",
+                feature, block
+            ),
+            FeatureError::UnknownBiome { feature, biome } => write!(
+                f,
+                "feature \"{}\" is placed in biome \"{}\", which doesn't exist",
+                feature, biome
+            ),
+            FeatureError::UnknownPaletteCharacter { feature, character } => write!(
+                f,
+                "structure \"{}\" uses '{}', which isn't in its palette",
+                feature, character
+            ),
+        }
+    }
+}
+
+impl std::error::Error for FeatureError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            FeatureError::Io { error, .. } => Some(error),
+            FeatureError::Parse { error, .. } => Some(error),
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,39 @@
+use crate::world::chunk::{BlockId, Chunk, ChunkPos, CHUNK_HEIGHT, CHUNK_WIDTH};
+
+use super::terrain_generator::{TerrainBlocks, TerrainGenerator};
+
+/// Generates the same horizontal layers of blocks everywhere.
+pub struct FlatGenerator {
+    // Listed from the bottom of the world upwards.
+    layers: Vec<BlockId>,
+}
+
+impl FlatGenerator {
+    pub fn new(mut layers: Vec<BlockId>) -> Self {
+        layers.truncate(CHUNK_HEIGHT);
+        Self { layers }
+    }
+
+    /// Bedrock, then stone, dirt and grass up to y = 63.
+    pub fn with_default_layers(blocks: &TerrainBlocks) -> Self {
+        let mut layers = vec![blocks.bedrock];
+        layers.extend([blocks.stone; 59]);
+        layers.extend([blocks.dirt; 3]);
+        layers.push(blocks.grass);
+        Self::new(layers)
+    }
+}
+
+impl TerrainGenerator for FlatGenerator {
+    fn generate_chunk(&self, _pos: ChunkPos) -> Chunk {
+        let mut chunk = Chunk::new();
+        for (y, &block) in self.layers.iter().enumerate() {
+            for z in 0..CHUNK_WIDTH {
+                for x in 0..CHUNK_WIDTH {
+                    chunk.set_block(x, y, z, block);
+                }
+            }
+        }
+        chunk
+    }
+}
@@ -0,0 +1,12 @@
+pub mod biome;
+pub mod biome_map;
+pub mod checkerboard;
+pub mod decorator;
+pub mod feature;
+pub mod flat;
+pub mod noise_caves;
+pub mod noise_terrain;
+pub mod seed;
+pub mod terrain_generator;
+pub mod void;
+pub mod worm_carver;
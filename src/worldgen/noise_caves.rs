@@ -0,0 +1,146 @@
+use noise::{Fbm, MultiFractal, NoiseFn, Perlin};
+
+use crate::world::chunk::{Chunk, ChunkPos, AIR, CHUNK_HEIGHT, CHUNK_WIDTH};
+
+use super::{seed, terrain_generator::TerrainBlocks};
+
+const CHEESE_SALT: u32 = 20;
+const SPAGHETTI_SALT: [u32; 2] = [21, 22];
+
+/// Noise above this is carved into large open caverns.
+const CHEESE_THRESHOLD: f64 = 0.6;
+/// Tunnels are carved where both spaghetti noises are closer to 0 than this.
+const SPAGHETTI_WIDTH: f64 = 0.04;
+/// Caverns stay at least this far below the surface, so they don't swallow
+/// hillsides. Tunnels may still break through and make cave entrances.
+const CHEESE_SURFACE_MARGIN: f64 = 8.0;
+/// Nothing is carved this close to the bottom of the world.
+const MIN_CAVE_HEIGHT: usize = 6;
+
+/// Noise is sampled on a coarser grid than blocks and interpolated between,
+/// which is much cheaper and smooths the caves out.
+const GRID_STEP_XZ: usize = 4;
+const GRID_STEP_Y: usize = 4;
+const GRID_SIZE_XZ: usize = CHUNK_WIDTH / GRID_STEP_XZ + 1;
+const GRID_SIZE_Y: usize = CHUNK_HEIGHT / GRID_STEP_Y + 1;
+
+/// Carves "cheese" caverns, where one 3D noise is high, and "spaghetti"
+/// tunnels, along the lines where two 3D noises both cross 0.
+///
+/// The noise only depends on world position, so caves line up across chunk
+/// borders without needing anything from neighbouring chunks.
+pub struct NoiseCaves {
+    blocks: TerrainBlocks,
+    cheese_noise: Fbm<Perlin>,
+    spaghetti_noise: [Fbm<Perlin>; 2],
+}
+
+impl NoiseCaves {
+    pub fn new(world_seed: u64, blocks: TerrainBlocks) -> Self {
+        Self {
+            blocks,
+            cheese_noise: Fbm::<Perlin>::new(seed::derive_seed(world_seed, CHEESE_SALT))
+                .set_octaves(2)
+                .set_frequency(1.0 / 64.0)
+                .set_lacunarity(2.0),
+            spaghetti_noise: SPAGHETTI_SALT.map(|salt| {
+                Fbm::<Perlin>::new(seed::derive_seed(world_seed, salt))
+                    .set_octaves(1)
+                    .set_frequency(1.0 / 48.0)
+            }),
+        }
+    }
+
+    /// `surface_heights` holds the height map surface of each column, laid
+    /// out x first then z.
+    pub fn carve(&self, chunk: &mut Chunk, pos: ChunkPos, surface_heights: &[f64]) {
+        let cheese = self.sample_grid(&self.cheese_noise, pos);
+        let spaghetti = self
+            .spaghetti_noise
+            .each_ref()
+            .map(|noise| self.sample_grid(noise, pos));
+
+        for z in 0..CHUNK_WIDTH {
+            for x in 0..CHUNK_WIDTH {
+                let surface_height = surface_heights[z * CHUNK_WIDTH + x];
+                let top = (surface_height.max(0.0) as usize + 1).min(CHUNK_HEIGHT);
+                for y in MIN_CAVE_HEIGHT..top {
+                    if !is_carvable(&self.blocks, chunk, x, y, z) {
+                        continue;
+                    }
+
+                    let is_cavern = (y as f64) < surface_height - CHEESE_SURFACE_MARGIN
+                        && interpolate(&cheese, x, y, z) > CHEESE_THRESHOLD;
+                    let is_tunnel = spaghetti
+                        .iter()
+                        .all(|noise| interpolate(noise, x, y, z).abs() < SPAGHETTI_WIDTH);
+                    if is_cavern || is_tunnel {
+                        chunk.set_block(x, y, z, AIR);
+                    }
+                }
+            }
+        }
+    }
+
+    fn sample_grid(&self, noise: &Fbm<Perlin>, pos: ChunkPos) -> Vec<f64> {
+        let origin = pos.origin();
+        let mut grid = Vec::with_capacity(GRID_SIZE_XZ * GRID_SIZE_Y * GRID_SIZE_XZ);
+        for gy in 0..GRID_SIZE_Y {
+            for gz in 0..GRID_SIZE_XZ {
+                for gx in 0..GRID_SIZE_XZ {
+                    grid.push(noise.get([
+                        (origin.x + (gx * GRID_STEP_XZ) as i32) as f64,
+                        (gy * GRID_STEP_Y) as f64,
+                        (origin.z + (gz * GRID_STEP_XZ) as i32) as f64,
+                    ]));
+                }
+            }
+        }
+        grid
+    }
+}
+
+/// Whether a cave may replace a block with air.
+///
+/// Water and bedrock are never carved, and neither is anything directly under
+/// water, so the sea never hangs over a hole.
+pub fn is_carvable(blocks: &TerrainBlocks, chunk: &Chunk, x: usize, y: usize, z: usize) -> bool {
+    let block = chunk.get_block(x, y, z);
+    block != AIR
+        && block != blocks.water
+        && block != blocks.bedrock
+        && (y + 1 >= CHUNK_HEIGHT || chunk.get_block(x, y + 1, z) != blocks.water)
+}
+
+/// Trilinearly interpolates a grid from `NoiseCaves::sample_grid` at a block
+/// position local to the chunk.
+fn interpolate(grid: &[f64], x: usize, y: usize, z: usize) -> f64 {
+    let (gx, tx) = (
+        x / GRID_STEP_XZ,
+        (x % GRID_STEP_XZ) as f64 / GRID_STEP_XZ as f64,
+    );
+    let (gy, ty) = (
+        y / GRID_STEP_Y,
+        (y % GRID_STEP_Y) as f64 / GRID_STEP_Y as f64,
+    );
+    let (gz, tz) = (
+        z / GRID_STEP_XZ,
+        (z % GRID_STEP_XZ) as f64 / GRID_STEP_XZ as f64,
+    );
+    let at = |dx: usize, dy: usize, dz: usize| {
+        grid[((gy + dy) * GRID_SIZE_XZ + gz + dz) * GRID_SIZE_XZ + gx + dx]
+    };
+    let lerp = |a: f64, b: f64, t: f64| a + (b - a) * t;
+
+    let bottom = lerp(
+        lerp(at(0, 0, 0), at(1, 0, 0), tx),
+        lerp(at(0, 0, 1), at(1, 0, 1), tx),
+        tz,
+    );
+    let top = lerp(
+        lerp(at(0, 1, 0), at(1, 1, 0), tx),
+        lerp(at(0, 1, 1), at(1, 1, 1), tx),
+        tz,
+    );
+    lerp(bottom, top, ty)
+}
@@ -0,0 +1,300 @@
+use noise::{Fbm, MultiFractal, NoiseFn, Perlin};
+
+use crate::world::chunk::{Chunk, ChunkPos, AIR, CHUNK_HEIGHT, CHUNK_WIDTH};
+
+use super::{
+    biome::Biome,
+    biome_map::BiomeMap,
+    noise_caves::NoiseCaves,
+    seed,
+    terrain_generator::{TerrainBlocks, TerrainGenerator},
+    worm_carver::WormCarver,
+};
+
+/// Height of the sea surface. Air below it is filled with water.
+pub const SEA_LEVEL: usize = 64;
+
+/// How far 3D noise can push the surface up or down, in blocks. This is what
+/// carves overhangs and floating bits out of the height map.
+const OVERHANG_STRENGTH: f64 = 10.0;
+/// Number of dirt blocks between the surface and stone.
+const SOIL_DEPTH: usize = 3;
+/// Bedrock can reach this far above the bottom of the world.
+const BEDROCK_DEPTH: usize = 4;
+
+const HEIGHT_SALT: u32 = 1;
+const OVERHANG_SALT: u32 = 2;
+const BEDROCK_SALT: u32 = 3;
+
+/// Generates hilly terrain from a 2D height map, roughened by 3D noise and
+/// shaped by the biome of each column, then carves caves out of it.
+pub struct NoiseTerrainGenerator {
+    blocks: TerrainBlocks,
+    biome_map: BiomeMap,
+    noise_caves: NoiseCaves,
+    worm_carver: WormCarver,
+    height_noise: Fbm<Perlin>,
+    overhang_noise: Fbm<Perlin>,
+    bedrock_seed: u64,
+}
+
+impl NoiseTerrainGenerator {
+    pub fn new(world_seed: u64, blocks: TerrainBlocks, biome_map: BiomeMap) -> Self {
+        Self {
+            blocks,
+            biome_map,
+            noise_caves: NoiseCaves::new(world_seed, blocks),
+            worm_carver: WormCarver::new(world_seed, blocks),
+            height_noise: Fbm::<Perlin>::new(seed::derive_seed(world_seed, HEIGHT_SALT))
+                .set_octaves(5)
+                .set_frequency(1.0 / 256.0)
+                .set_lacunarity(2.0),
+            overhang_noise: Fbm::<Perlin>::new(seed::derive_seed(world_seed, OVERHANG_SALT))
+                .set_octaves(3)
+                .set_frequency(1.0 / 48.0)
+                .set_lacunarity(2.0),
+            bedrock_seed: seed::derive_seed(world_seed, BEDROCK_SALT) as u64,
+        }
+    }
+
+    pub fn biome_map(&self) -> &BiomeMap {
+        &self.biome_map
+    }
+
+    /// Height the surface would be at without any overhangs.
+    fn surface_height(&self, x: i32, z: i32, base_height: f64, height_amplitude: f64) -> f64 {
+        base_height + self.height_noise.get([x as f64, z as f64]) * height_amplitude
+    }
+
+    fn is_solid(&self, x: i32, y: i32, z: i32, surface_height: f64) -> bool {
+        let depth = surface_height - y as f64;
+        // Far from the surface the overhang noise can't change anything, so
+        // skip sampling it.
+        if depth.abs() > OVERHANG_STRENGTH {
+            return depth > 0.0;
+        }
+        depth + self.overhang_noise.get([x as f64, y as f64, z as f64]) * OVERHANG_STRENGTH > 0.0
+    }
+
+    /// Shapes the terrain out of stone, then fills the sea with water.
+    fn fill_column(
+        &self,
+        chunk: &mut Chunk,
+        (x, z): (usize, usize),
+        (world_x, world_z): (i32, i32),
+        surface_height: f64,
+    ) {
+        for y in 0..CHUNK_HEIGHT {
+            let block = if self.is_solid(world_x, y as i32, world_z, surface_height) {
+                self.blocks.stone
+            } else if y < SEA_LEVEL {
+                self.blocks.water
+            } else {
+                AIR
+            };
+            chunk.set_block(x, y, z, block);
+        }
+    }
+
+    /// Replaces the top layers of stone with the biome's soil, working down
+    /// from the top of the column, and lays bedrock at the bottom.
+    fn surface_column(
+        &self,
+        chunk: &mut Chunk,
+        (x, z): (usize, usize),
+        (world_x, world_z): (i32, i32),
+        biome: &Biome,
+    ) {
+        // Number of solid blocks since the last non-solid one.
+        let mut depth = 0;
+        let mut underwater = false;
+        for y in (0..CHUNK_HEIGHT).rev() {
+            let block = chunk.get_block(x, y, z);
+            if block != self.blocks.stone {
+                depth = 0;
+                underwater = block == self.blocks.water;
+                continue;
+            }
+
+            let surface = match depth {
+                0 if !underwater && y >= SEA_LEVEL + 2 => Some(biome.surface),
+                depth if depth <= SOIL_DEPTH => Some(if underwater {
+                    self.blocks.gravel
+                } else if y < SEA_LEVEL + 2 {
+                    self.blocks.sand
+                } else {
+                    biome.filler
+                }),
+                _ => None,
+            };
+            if let Some(surface) = surface {
+                chunk.set_block(x, y, z, surface);
+            }
+            depth += 1;
+        }
+
+        for y in 0..BEDROCK_DEPTH {
+            let chance = 1.0 - y as f64 / BEDROCK_DEPTH as f64;
+            if seed::random_at(self.bedrock_seed, world_x, y as i32, world_z) < chance {
+                chunk.set_block(x, y, z, self.blocks.bedrock);
+            }
+        }
+    }
+}
+
+impl TerrainGenerator for NoiseTerrainGenerator {
+    fn generate_chunk(&self, pos: ChunkPos) -> Chunk {
+        let mut chunk = Chunk::new();
+        let origin = pos.origin();
+        let biomes = self.biome_map.sample_chunk(pos);
+        let mut surface_heights = [0.0; CHUNK_WIDTH * CHUNK_WIDTH];
+
+        for z in 0..CHUNK_WIDTH {
+            for x in 0..CHUNK_WIDTH {
+                let world = (origin.x + x as i32, origin.z + z as i32);
+                let index = z * CHUNK_WIDTH + x;
+                let biome = biomes.ids[index];
+                let surface_height = self.surface_height(
+                    world.0,
+                    world.1,
+                    biomes.base_heights[index],
+                    biomes.height_amplitudes[index],
+                );
+
+                surface_heights[index] = surface_height;
+                chunk.set_biome(x, z, biome);
+                self.fill_column(&mut chunk, (x, z), world, surface_height);
+                self.surface_column(
+                    &mut chunk,
+                    (x, z),
+                    world,
+                    self.biome_map.biomes().get(biome),
+                );
+            }
+        }
+
+        // Caves are carved after the surface is laid, so that cave floors
+        // don't get covered in grass.
+        self.noise_caves.carve(&mut chunk, pos, &surface_heights);
+        self.worm_carver.carve(&mut chunk, pos);
+
+        chunk
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use std::{
+        collections::{hash_map::DefaultHasher, HashMap},
+        hash::{Hash, Hasher},
+        thread,
+    };
+
+    use crate::{
+        block::registry::BlockRegistry,
+        worldgen::{biome::Biomes, seed::hash_position},
+    };
+
+    use super::*;
+
+    pub(crate) const SEED: u64 = 0x5eed;
+
+    pub(crate) fn registry() -> BlockRegistry {
+        BlockRegistry::load("src/assets/blocks.ron", "src/assets/textures").unwrap()
+    }
+
+    pub(crate) fn generator(seed: u64) -> NoiseTerrainGenerator {
+        let registry = registry();
+        let blocks = TerrainBlocks::from_registry(&registry).unwrap();
+        let biomes = Biomes::from_registry(&registry).unwrap();
+        NoiseTerrainGenerator::new(seed, blocks, BiomeMap::new(seed, biomes))
+    }
+
+    /// Every chunk within `radius` chunks of the origin, row by row,
+    /// including chunks at negative positions.
+    pub(crate) fn region(radius: i32) -> Vec<ChunkPos> {
+        (-radius..=radius)
+            .flat_map(|z| (-radius..=radius).map(move |x| ChunkPos::new(x, z)))
+            .collect()
+    }
+
+    /// The same positions in a scrambled order that's the same every run.
+    pub(crate) fn shuffled(positions: &[ChunkPos], seed: u64) -> Vec<ChunkPos> {
+        let mut positions = positions.to_vec();
+        for i in (1..positions.len()).rev() {
+            let j = hash_position(seed, i as i32, 0, 0) % (i as u64 + 1);
+            positions.swap(i, j as usize);
+        }
+        positions
+    }
+
+    fn chunk_hash(chunk: &Chunk) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        chunk.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    fn generate_all(
+        generator: &NoiseTerrainGenerator,
+        positions: &[ChunkPos],
+    ) -> HashMap<ChunkPos, u64> {
+        positions
+            .iter()
+            .map(|&pos| (pos, chunk_hash(&generator.generate_chunk(pos))))
+            .collect()
+    }
+
+    #[test]
+    fn generators_with_the_same_seed_generate_the_same_chunks() {
+        let positions = region(1);
+        assert_eq!(
+            generate_all(&generator(SEED), &positions),
+            generate_all(&generator(SEED), &positions)
+        );
+    }
+
+    #[test]
+    fn chunks_are_the_same_whatever_order_they_are_generated_in() {
+        let generator = generator(SEED);
+        let forward = region(1);
+        let mut reversed = forward.clone();
+        reversed.reverse();
+        let expected = generate_all(&generator, &forward);
+
+        assert_eq!(generate_all(&generator, &reversed), expected);
+        assert_eq!(generate_all(&generator, &shuffled(&forward, 1)), expected);
+    }
+
+    #[test]
+    fn chunks_are_the_same_whichever_thread_generates_them() {
+        let generator = generator(SEED);
+        let positions = region(1);
+        let expected = generate_all(&generator, &positions);
+
+        let generated: Vec<_> = thread::scope(|scope| {
+            let threads: Vec<_> = (0..3)
+                .map(|seed| {
+                    let order = shuffled(&positions, seed);
+                    let generator = &generator;
+                    scope.spawn(move || generate_all(generator, &order))
+                })
+                .collect();
+            threads
+                .into_iter()
+                .map(|thread| thread.join().unwrap())
+                .collect()
+        });
+        for hashes in generated {
+            assert_eq!(hashes, expected);
+        }
+    }
+
+    #[test]
+    fn different_seeds_generate_different_chunks() {
+        let pos = ChunkPos::new(0, 0);
+        assert_ne!(
+            chunk_hash(&generator(SEED).generate_chunk(pos)),
+            chunk_hash(&generator(SEED + 1).generate_chunk(pos))
+        );
+    }
+}
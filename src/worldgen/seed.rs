@@ -0,0 +1,76 @@
+use crate::world::chunk::ChunkPos;
+
+/// Mixes a 64 bit value into a well distributed hash (SplitMix64).
+fn mix(mut value: u64) -> u64 {
+    value = value.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    value = (value ^ (value >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    value = (value ^ (value >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    value ^ (value >> 31)
+}
+
+/// Derives an independent seed for one noise source from the world seed.
+///
+/// Each source uses a different `salt`, so that e.g. the height map and cave
+/// noise aren't correlated.
+pub fn derive_seed(world_seed: u64, salt: u32) -> u32 {
+    mix(world_seed ^ mix(salt as u64)) as u32
+}
+
+/// Hashes a block position, giving a value that depends only on the seed and
+/// the position.
+pub fn hash_position(seed: u64, x: i32, y: i32, z: i32) -> u64 {
+    let mut hash = mix(seed);
+    for coordinate in [x, y, z] {
+        hash = mix(hash ^ coordinate as u32 as u64);
+    }
+    hash
+}
+
+/// Like `hash_position`, mapped to a number between 0 and 1.
+pub fn random_at(seed: u64, x: i32, y: i32, z: i32) -> f64 {
+    (hash_position(seed, x, y, z) >> 11) as f64 / (1u64 << 53) as f64
+}
+
+/// Small deterministic random number generator (SplitMix64).
+///
+/// Unlike `rand`'s generators, its output is guaranteed never to change
+/// between versions, which matters because worlds are generated from it.
+pub struct WorldRng {
+    state: u64,
+}
+
+impl WorldRng {
+    pub fn new(seed: u64) -> Self {
+        Self { state: seed }
+    }
+
+    /// A generator unique to one chunk and one use of it, given by `salt`.
+    pub fn for_chunk(world_seed: u64, salt: u32, pos: ChunkPos) -> Self {
+        Self::new(hash_position(
+            world_seed ^ mix(salt as u64),
+            pos.x,
+            0,
+            pos.z,
+        ))
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        let value = mix(self.state);
+        self.state = self.state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        value
+    }
+
+    /// Returns a number between 0 (inclusive) and 1 (exclusive).
+    pub fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    pub fn range(&mut self, min: f64, max: f64) -> f64 {
+        min + self.next_f64() * (max - min)
+    }
+
+    /// Returns a whole number between 0 (inclusive) and `max` (exclusive).
+    pub fn below(&mut self, max: u32) -> u32 {
+        (self.next_f64() * max as f64) as u32
+    }
+}
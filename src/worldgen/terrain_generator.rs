@@ -0,0 +1,39 @@
+use crate::{
+    block::registry::{BlockRegistry, BlockRegistryError},
+    world::chunk::{BlockId, Chunk, ChunkPos},
+};
+
+/// Fills in the blocks of newly created chunks.
+///
+/// Generators must be pure functions of their settings and the chunk
+/// position, so that chunks come out the same no matter which order they're
+/// generated in, or which thread generates them.
+pub trait TerrainGenerator: Send + Sync {
+    fn generate_chunk(&self, pos: ChunkPos) -> Chunk;
+}
+
+/// IDs of the blocks terrain is built from.
+#[derive(Debug, Clone, Copy)]
+pub struct TerrainBlocks {
+    pub stone: BlockId,
+    pub dirt: BlockId,
+    pub grass: BlockId,
+    pub sand: BlockId,
+    pub gravel: BlockId,
+    pub water: BlockId,
+    pub bedrock: BlockId,
+}
+
+impl TerrainBlocks {
+    pub fn from_registry(registry: &BlockRegistry) -> Result<Self, BlockRegistryError> {
+        Ok(Self {
+            stone: registry.require("stone")?,
+            dirt: registry.require("dirt")?,
+            grass: registry.require("grass")?,
+            sand: registry.require("sand")?,
+            gravel: registry.require("gravel")?,
+            water: registry.require("water")?,
+            bedrock: registry.require("bedrock")?,
+        })
+    }
+}
@@ -0,0 +1,12 @@
+use crate::world::chunk::{Chunk, ChunkPos};
+
+use super::terrain_generator::TerrainGenerator;
+
+/// Generates nothing but air.
+pub struct VoidGenerator;
+
+impl TerrainGenerator for VoidGenerator {
+    fn generate_chunk(&self, _pos: ChunkPos) -> Chunk {
+        Chunk::new()
+    }
+}
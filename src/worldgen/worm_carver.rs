@@ -0,0 +1,318 @@
+use std::f64::consts::PI;
+
+use crate::world::chunk::{Chunk, ChunkPos, AIR, CHUNK_HEIGHT, CHUNK_WIDTH};
+
+use super::{noise_caves::is_carvable, seed::WorldRng, terrain_generator::TerrainBlocks};
+
+const CARVER_SALT: u32 = 30;
+
+/// How many chunks away a worm can start and still reach a chunk. Worms are
+/// kept short enough that they never travel further than this.
+const CARVER_RANGE: i32 = 4;
+
+const CAVE_CHANCE: f64 = 0.12;
+const MAX_CAVES: u32 = 3;
+const RAVINE_CHANCE: f64 = 0.02;
+
+/// Nothing is carved this close to the bottom of the world.
+const MIN_CARVE_HEIGHT: i32 = 6;
+
+/// Carves winding tunnels ("worms") and ravines, which can run for several
+/// chunks.
+///
+/// Worms that start in one chunk have to be carved into every chunk they pass
+/// through, whichever order those chunks are generated in. So rather than a
+/// chunk carving its own worms into its neighbours, every chunk re-traces all
+/// the worms that start within `CARVER_RANGE` chunks of it, from a random
+/// generator seeded by the chunk the worm starts in, and keeps only the parts
+/// that fall inside itself. A worm therefore always takes the same path, and
+/// each chunk it crosses carves its own share of it.
+pub struct WormCarver {
+    world_seed: u64,
+    blocks: TerrainBlocks,
+}
+
+struct Worm {
+    position: [f64; 3],
+    yaw: f64,
+    pitch: f64,
+    length: u32,
+    max_radius: f64,
+    /// How much taller than wide the tunnel is. Ravines are tall and narrow.
+    vertical_scale: f64,
+    /// How quickly the worm levels out after turning up or down.
+    pitch_damping: f64,
+}
+
+impl WormCarver {
+    pub fn new(world_seed: u64, blocks: TerrainBlocks) -> Self {
+        Self { world_seed, blocks }
+    }
+
+    pub fn carve(&self, chunk: &mut Chunk, pos: ChunkPos) {
+        for dz in -CARVER_RANGE..=CARVER_RANGE {
+            for dx in -CARVER_RANGE..=CARVER_RANGE {
+                let source = pos.offset(dx, dz);
+                let mut rng = WorldRng::for_chunk(self.world_seed, CARVER_SALT, source);
+
+                if rng.next_f64() < CAVE_CHANCE {
+                    for _ in 0..1 + rng.below(MAX_CAVES) {
+                        let worm = Worm::cave(&mut rng, source);
+                        self.carve_worm(chunk, pos, worm, &mut rng);
+                    }
+                }
+                if rng.next_f64() < RAVINE_CHANCE {
+                    let worm = Worm::ravine(&mut rng, source);
+                    self.carve_worm(chunk, pos, worm, &mut rng);
+                }
+            }
+        }
+    }
+
+    /// Traces a worm from start to end, carving the parts inside the chunk.
+    ///
+    /// The random generator has to be used the same way whether or not the
+    /// worm ever reaches the chunk, or worms after it would change too.
+    fn carve_worm(&self, chunk: &mut Chunk, pos: ChunkPos, mut worm: Worm, rng: &mut WorldRng) {
+        let mut yaw_change = 0.0;
+        let mut pitch_change = 0.0;
+
+        for step in 0..worm.length {
+            let progress = step as f64 / worm.length as f64;
+            // Widest in the middle, narrowing towards both ends.
+            let radius = 1.5 + (progress * PI).sin() * worm.max_radius;
+
+            worm.position[0] += worm.yaw.cos() * worm.pitch.cos();
+            worm.position[1] += worm.pitch.sin();
+            worm.position[2] += worm.yaw.sin() * worm.pitch.cos();
+
+            worm.pitch *= worm.pitch_damping;
+            worm.pitch += pitch_change * 0.1;
+            worm.yaw += yaw_change * 0.1;
+            pitch_change = pitch_change * 0.9 + (rng.next_f64() - rng.next_f64()) * 2.0;
+            yaw_change = yaw_change * 0.75 + (rng.next_f64() - rng.next_f64()) * 4.0;
+
+            self.carve_ellipsoid(
+                chunk,
+                pos,
+                worm.position,
+                radius,
+                radius * worm.vertical_scale,
+            );
+        }
+    }
+
+    fn carve_ellipsoid(
+        &self,
+        chunk: &mut Chunk,
+        pos: ChunkPos,
+        center: [f64; 3],
+        radius: f64,
+        vertical_radius: f64,
+    ) {
+        let origin = pos.origin();
+        let local = [
+            center[0] - origin.x as f64,
+            center[1],
+            center[2] - origin.z as f64,
+        ];
+        let range = |center: f64, radius: f64, min: i32, max: i32| {
+            ((center - radius).floor() as i32).max(min)..((center + radius).ceil() as i32).min(max)
+        };
+
+        let width = CHUNK_WIDTH as i32;
+        for y in range(
+            local[1],
+            vertical_radius,
+            MIN_CARVE_HEIGHT,
+            CHUNK_HEIGHT as i32,
+        ) {
+            for z in range(local[2], radius, 0, width) {
+                for x in range(local[0], radius, 0, width) {
+                    let dx = (x as f64 + 0.5 - local[0]) / radius;
+                    let dy = (y as f64 + 0.5 - local[1]) / vertical_radius;
+                    let dz = (z as f64 + 0.5 - local[2]) / radius;
+                    if dx * dx + dy * dy + dz * dz >= 1.0 {
+                        continue;
+                    }
+
+                    let (x, y, z) = (x as usize, y as usize, z as usize);
+                    if is_carvable(&self.blocks, chunk, x, y, z) {
+                        chunk.set_block(x, y, z, AIR);
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl Worm {
+    fn cave(rng: &mut WorldRng, source: ChunkPos) -> Self {
+        Self {
+            position: Self::start_position(rng, source, 12.0, 64.0),
+            yaw: rng.range(0.0, 2.0 * PI),
+            pitch: rng.range(-0.25, 0.25),
+            length: 32 + rng.below(24),
+            max_radius: rng.range(1.0, 3.5),
+            vertical_scale: 1.0,
+            pitch_damping: 0.7,
+        }
+    }
+
+    fn ravine(rng: &mut WorldRng, source: ChunkPos) -> Self {
+        Self {
+            position: Self::start_position(rng, source, 20.0, 50.0),
+            yaw: rng.range(0.0, 2.0 * PI),
+            pitch: rng.range(-0.1, 0.1),
+            length: 40 + rng.below(16),
+            max_radius: rng.range(1.5, 2.5),
+            vertical_scale: 3.0,
+            pitch_damping: 0.5,
+        }
+    }
+
+    fn start_position(rng: &mut WorldRng, source: ChunkPos, min_y: f64, max_y: f64) -> [f64; 3] {
+        let origin = source.origin();
+        [
+            origin.x as f64 + rng.range(0.0, CHUNK_WIDTH as f64),
+            rng.range(min_y, max_y),
+            origin.z as f64 + rng.range(0.0, CHUNK_WIDTH as f64),
+        ]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use ultraviolet as uv;
+
+    use crate::{
+        block::registry::BlockRegistry,
+        world::{lighting::Lighting, world::World},
+        worldgen::noise_terrain::tests::{region, registry, shuffled, SEED},
+    };
+
+    use super::*;
+
+    /// Chunks are solid up to here, so the highest tunnels open up to the
+    /// sky and let light in.
+    const GROUND_HEIGHT: usize = 60;
+
+    /// Light sources are buried this deep, so that tunnels passing through
+    /// carry their light along, across chunk borders.
+    const GLOWSTONE_HEIGHT: usize = 30;
+
+    /// Solid ground with a layer of light sources buried in it.
+    fn ground(registry: &BlockRegistry) -> Chunk {
+        let stone = registry.require("stone").unwrap();
+        let glowstone = registry.require("glowstone").unwrap();
+        let mut chunk = Chunk::new();
+        for y in 0..GROUND_HEIGHT {
+            let block = if y == GLOWSTONE_HEIGHT {
+                glowstone
+            } else {
+                stone
+            };
+            for z in 0..CHUNK_WIDTH {
+                for x in 0..CHUNK_WIDTH {
+                    chunk.set_block(x, y, z, block);
+                }
+            }
+        }
+        chunk
+    }
+
+    fn carve_all(positions: &[ChunkPos]) -> HashMap<ChunkPos, Chunk> {
+        let registry = registry();
+        let carver = WormCarver::new(SEED, TerrainBlocks::from_registry(&registry).unwrap());
+        positions
+            .iter()
+            .map(|&pos| {
+                let mut chunk = ground(&registry);
+                carver.carve(&mut chunk, pos);
+                (pos, chunk)
+            })
+            .collect()
+    }
+
+    /// Carves each chunk and adds it to a world in the given order, lighting
+    /// it the way `ChunkManager` does.
+    fn load_in_order(order: &[ChunkPos]) -> World {
+        let lighting = Lighting::from_registry(&registry());
+        let mut carved = carve_all(order);
+        let mut world = World::new();
+        for pos in order {
+            let mut chunk = carved.remove(pos).unwrap();
+            lighting.light_chunk(&mut chunk);
+            world.insert_generated_chunk(*pos, chunk, Vec::new());
+            lighting.stitch_chunk(&mut world, *pos);
+        }
+        world
+    }
+
+    fn assert_same_world(world: &World, expected: &World) {
+        assert_eq!(world.chunk_count(), expected.chunk_count());
+        for (pos, chunk) in expected.chunks() {
+            assert!(
+                world.chunk(*pos) == Some(chunk),
+                "chunk {:?} has different blocks or light",
+                pos
+            );
+        }
+    }
+
+    fn orders() -> Vec<Vec<ChunkPos>> {
+        let forward = region(2);
+        let mut reversed = forward.clone();
+        reversed.reverse();
+        vec![
+            reversed,
+            shuffled(&forward, 1),
+            shuffled(&forward, 2),
+            forward,
+        ]
+    }
+
+    #[test]
+    fn tunnels_cross_chunk_borders() {
+        let world = load_in_order(&region(2));
+        let width = CHUNK_WIDTH as i32;
+        let open_across_border = |x: i32, y: i32, z: i32| {
+            world.get_block(uv::IVec3::new(x - 1, y, z)) == Some(AIR)
+                && world.get_block(uv::IVec3::new(x, y, z)) == Some(AIR)
+        };
+        // Borders between columns of chunks, underground.
+        let crossings = (-1..=2)
+            .flat_map(|border| (1..GROUND_HEIGHT as i32).map(move |y| (border * width, y)))
+            .flat_map(|(x, y)| (-2 * width..3 * width).map(move |z| (x, y, z)))
+            .filter(|&(x, y, z)| open_across_border(x, y, z))
+            .count();
+        assert!(crossings > 0);
+    }
+
+    #[test]
+    fn carving_is_the_same_whatever_order_chunks_are_generated_in() {
+        let orders = orders();
+        let expected = carve_all(&orders[0]);
+        for order in &orders[1..] {
+            let carved = carve_all(order);
+            for (pos, chunk) in &expected {
+                assert!(
+                    carved[pos] == *chunk,
+                    "chunk {:?} was carved differently",
+                    pos
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn light_is_the_same_whatever_order_chunks_are_loaded_in() {
+        let orders = orders();
+        let expected = load_in_order(&orders[0]);
+        for order in &orders[1..] {
+            assert_same_world(&load_in_order(order), &expected);
+        }
+    }
+}